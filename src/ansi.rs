@@ -0,0 +1,247 @@
+//! Conversion of terminal output containing ANSI escape sequences to HTML.
+//!
+//! Only SGR sequences (`ESC [ ... m`) are rendered, all other control sequences
+//! (cursor movement, erasing, OSC titles/links, ...) are removed.
+
+use std::fmt::Write;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// Returns whether `text` looks like it contains ANSI escape sequences.
+pub fn detect(text: &str) -> bool {
+    text.contains("\x1b[")
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Color {
+    /// One of the 16 named colors, rendered using CSS classes to follow the
+    /// color scheme.
+    Named(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn css(self) -> String {
+        match self {
+            Color::Named(_) => unreachable!("named colors use classes"),
+            Color::Indexed(index @ 16..=231) => {
+                const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+                let index = index - 16;
+                format!(
+                    "#{:02x}{:02x}{:02x}",
+                    LEVELS[(index / 36) as usize],
+                    LEVELS[(index / 6 % 6) as usize],
+                    LEVELS[(index % 6) as usize]
+                )
+            }
+            Color::Indexed(index) => {
+                let gray = 8 + (index.saturating_sub(232)) * 10;
+                format!("#{gray:02x}{gray:02x}{gray:02x}")
+            }
+            Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    inverse: bool,
+    strike: bool,
+}
+
+impl Style {
+    fn apply(&mut self, params: &[u16]) {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                9 => self.strike = true,
+                21 | 22 => (self.bold, self.dim) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                29 => self.strike = false,
+                30..=37 => self.fg = Some(Color::Named(param as u8 - 30)),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(Color::Named(param as u8 - 40)),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(Color::Named(param as u8 - 90 + 8)),
+                100..=107 => self.bg = Some(Color::Named(param as u8 - 100 + 8)),
+                38 | 48 => {
+                    // Extended colors that are malformed end processing of this
+                    // sequence, as the remaining parameters are meaningless.
+                    let Some(color) = extended_color(&mut params) else {
+                        return;
+                    };
+                    if param == 38 {
+                        self.fg = Some(color);
+                    } else {
+                        self.bg = Some(color);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn open(&self, out: &mut String) {
+        let (mut fg, mut bg) = (self.fg, self.bg);
+        if self.inverse {
+            (fg, bg) = (bg, fg);
+        }
+        let mut classes = Vec::new();
+        let mut styles = Vec::new();
+        for (color, kind) in [(fg, "fg"), (bg, "bg")] {
+            match color {
+                Some(Color::Named(index)) => classes.push(format!("ansi-{kind}-{index}")),
+                Some(color) => styles.push(format!(
+                    "{}:{}",
+                    if kind == "fg" {
+                        "color"
+                    } else {
+                        "background-color"
+                    },
+                    color.css()
+                )),
+                // Inverted default colors
+                None if self.inverse => classes.push(format!("ansi-{kind}-inverse")),
+                None => {}
+            }
+        }
+        for (set, class) in [
+            (self.bold, "ansi-bold"),
+            (self.dim, "ansi-dim"),
+            (self.italic, "ansi-italic"),
+            (self.underline, "ansi-underline"),
+            (self.strike, "ansi-strike"),
+        ] {
+            if set {
+                classes.push(class.to_owned());
+            }
+        }
+        out.push_str("<span");
+        if !classes.is_empty() {
+            write!(out, " class=\"{}\"", classes.join(" ")).expect("writing to string");
+        }
+        if !styles.is_empty() {
+            write!(out, " style=\"{}\"", styles.join(";")).expect("writing to string");
+        }
+        out.push('>');
+    }
+}
+
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match params.next()? {
+        5 => {
+            let index = u8::try_from(params.next()?).ok()?;
+            Some(if index < 16 {
+                Color::Named(index)
+            } else {
+                Color::Indexed(index)
+            })
+        }
+        2 => {
+            let mut component = || u8::try_from(params.next()?).ok();
+            Some(Color::Rgb(component()?, component()?, component()?))
+        }
+        _ => None,
+    }
+}
+
+fn escape(out: &mut String, c: char) {
+    match c {
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '&' => out.push_str("&amp;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&#x27;"),
+        c => out.push(c),
+    }
+}
+
+/// Converts `text` to HTML, rendering SGR sequences as styled `<span>`s.
+///
+/// Malformed or unsupported sequences are dropped, so the result only ever
+/// contains the escaped text and the generated spans.
+pub fn to_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut style = Style::default();
+    let mut open = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => match chars.next() {
+                // Control Sequence Introducer
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut terminator = None;
+                    // Malformed sequences end at the first character that is
+                    // neither a parameter nor a final byte.
+                    while let Some(c) = chars.next_if(|c| ('\x20'..='\x7e').contains(c)) {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            terminator = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if terminator != Some('m') {
+                        continue;
+                    }
+                    let Ok(params) = params
+                        .split([';', ':'])
+                        .map(|it| if it.is_empty() { Ok(0) } else { it.parse() })
+                        .collect::<Result<Vec<u16>, _>>()
+                    else {
+                        continue;
+                    };
+                    let previous = style;
+                    style.apply(&params);
+                    if previous != style {
+                        if open {
+                            out.push_str("</span>");
+                        }
+                        open = style != Style::default();
+                        if open {
+                            style.open(&mut out);
+                        }
+                    }
+                }
+                // Operating System Command, terminated by BEL or ST
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == BEL || c == ESC && chars.next_if_eq(&'\\').is_some() {
+                            break;
+                        }
+                    }
+                }
+                // Character set designations
+                Some('(' | ')') => {
+                    chars.next();
+                }
+                // Other two character sequences
+                _ => {}
+            },
+            '\r' => {}
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => escape(&mut out, c),
+        }
+    }
+    if open {
+        out.push_str("</span>");
+    }
+    out
+}
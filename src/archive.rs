@@ -0,0 +1,251 @@
+//! Read-only access to zip and tar archives held in memory, as well as
+//! creation of zips to download multi-file pastes.
+//!
+//! Nothing is ever extracted to disk, entries with paths escaping the archive
+//! root are ignored and decompression is bounded to guard against zip bombs.
+
+use std::{
+    io::{self, Cursor, Read, Write},
+    path::{Component, Path},
+};
+
+use actix_web::{http::StatusCode, ResponseError};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+
+/// Maximum number of entries listed.
+const MAX_ENTRIES: usize = 10_000;
+/// Maximum number of bytes decompressed for a single operation, bounds the
+/// work spent on compressed tar streams as well as single entries.
+const MAX_DECOMPRESSED: u64 = 100_000_000;
+/// Maximum ratio between the uncompressed and compressed size of a zip entry.
+const MAX_RATIO: u64 = 1_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Archive is invalid: {0}")]
+    Invalid(String),
+    #[error("Archive contains more than {MAX_ENTRIES} entries")]
+    TooManyEntries,
+    #[error("Entry is too large or suspiciously well compressed")]
+    TooLarge,
+}
+
+impl ResponseError for ArchiveError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ArchiveError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ArchiveError::TooManyEntries | ArchiveError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+}
+
+impl From<zip::result::ZipError> for ArchiveError {
+    fn from(value: zip::result::ZipError) -> Self {
+        Self::Invalid(value.to_string())
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(value: io::Error) -> Self {
+        if value.kind() == io::ErrorKind::FileTooLarge {
+            Self::TooLarge
+        } else {
+            Self::Invalid(value.to_string())
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Kind {
+    pub fn from_ext(ext: &str) -> Option<Self> {
+        match ext {
+            "zip" | "jar" => Some(Self::Zip),
+            "tar" => Some(Self::Tar),
+            "tar.gz" | "tgz" => Some(Self::TarGz),
+            _ => None,
+        }
+    }
+}
+
+pub struct Entry {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub is_dir: bool,
+}
+
+/// Normalizes `path`, returning `None` if it is absolute or contains `..`.
+fn enclosed(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Reader failing once more than `limit` bytes were read.
+struct Limited<R> {
+    inner: R,
+    limit: u64,
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.limit = self
+            .limit
+            .checked_sub(read as u64)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::FileTooLarge))?;
+        Ok(read)
+    }
+}
+
+fn tar(kind: Kind, data: &[u8]) -> tar::Archive<Limited<Box<dyn Read + '_>>> {
+    let reader: Box<dyn Read> = match kind {
+        Kind::TarGz => Box::new(GzDecoder::new(data)),
+        _ => Box::new(data),
+    };
+    tar::Archive::new(Limited {
+        inner: reader,
+        limit: MAX_DECOMPRESSED,
+    })
+}
+
+fn zip_time(time: zip::DateTime) -> Option<DateTime<Utc>> {
+    NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+        .and_hms_opt(
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )
+        .map(|it| it.and_utc())
+}
+
+/// Lists all entries of the archive.
+pub fn list(kind: Kind, data: &[u8]) -> Result<Vec<Entry>, ArchiveError> {
+    let mut entries = Vec::new();
+    match kind {
+        Kind::Zip => {
+            let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+            if archive.len() > MAX_ENTRIES {
+                return Err(ArchiveError::TooManyEntries);
+            }
+            for index in 0..archive.len() {
+                let file = archive.by_index_raw(index)?;
+                let Some(path) = file.enclosed_name().as_deref().and_then(enclosed) else {
+                    continue;
+                };
+                entries.push(Entry {
+                    path,
+                    size: file.size(),
+                    modified: file.last_modified().and_then(zip_time),
+                    is_dir: file.is_dir(),
+                });
+            }
+        }
+        Kind::Tar | Kind::TarGz => {
+            for entry in tar(kind, data).entries()? {
+                let entry = entry?;
+                if entries.len() >= MAX_ENTRIES {
+                    return Err(ArchiveError::TooManyEntries);
+                }
+                let Some(path) = enclosed(&entry.path()?) else {
+                    continue;
+                };
+                let header = entry.header();
+                entries.push(Entry {
+                    path,
+                    size: header.size()?,
+                    modified: header
+                        .mtime()
+                        .ok()
+                        .and_then(|it| DateTime::from_timestamp(it.try_into().ok()?, 0)),
+                    is_dir: header.entry_type().is_dir(),
+                });
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Reads the file at `path` from the archive, fails if it is larger than
+/// `limit` bytes.
+pub fn read(
+    kind: Kind,
+    data: &[u8],
+    path: &str,
+    limit: u64,
+) -> Result<Option<Vec<u8>>, ArchiveError> {
+    let limit = limit.min(MAX_DECOMPRESSED);
+    let read = |reader: &mut dyn Read, size: u64| {
+        if size > limit {
+            return Err(ArchiveError::TooLarge);
+        }
+        let mut buf = Vec::with_capacity(size as usize);
+        Limited {
+            inner: reader,
+            limit,
+        }
+        .read_to_end(&mut buf)?;
+        Ok(Some(buf))
+    };
+    match kind {
+        Kind::Zip => {
+            let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+            for index in 0..archive.len().min(MAX_ENTRIES) {
+                let file = archive.by_index_raw(index)?;
+                if file.is_dir()
+                    || file
+                        .enclosed_name()
+                        .as_deref()
+                        .and_then(enclosed)
+                        .as_deref()
+                        != Some(path)
+                {
+                    continue;
+                }
+                if file.size() > file.compressed_size().max(1).saturating_mul(MAX_RATIO) {
+                    return Err(ArchiveError::TooLarge);
+                }
+                drop(file);
+                let mut file = archive.by_index(index)?;
+                let size = file.size();
+                return read(&mut file, size);
+            }
+        }
+        Kind::Tar | Kind::TarGz => {
+            for entry in tar(kind, data).entries()? {
+                let mut entry = entry?;
+                if entry.header().entry_type().is_file()
+                    && enclosed(&entry.path()?).as_deref() == Some(path)
+                {
+                    let size = entry.header().size()?;
+                    return read(&mut entry, size);
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Creates a zip archive containing `files`.
+pub fn zip(files: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, ArchiveError> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in files {
+        archive.start_file(name, zip::write::SimpleFileOptions::default())?;
+        archive.write_all(&data)?;
+    }
+    Ok(archive.finish()?.into_inner())
+}
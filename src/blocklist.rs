@@ -0,0 +1,119 @@
+//! Blocklist of the SHA-256 hashes of contents that can not be uploaded, e.g.
+//! after they were taken down, see [`crate::db::BlockedHash`].
+//!
+//! Lists are plain text with a hex encoded hash per line, optionally followed
+//! by the reason. Empty lines and lines starting with `#` are ignored.
+
+use std::fs;
+
+use actix_web::{http::StatusCode, ResponseError};
+use anyhow::{bail, Context, Result};
+
+use crate::db::{BlockedHash, DB};
+
+const USAGE: &str =
+    "Usage: pastemp blocklist (list | import <file> | add <hash> [reason] | remove <hash>...)";
+
+pub struct Entry {
+    pub hash: String,
+    pub reason: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Line {line}: `{hash}` is not a SHA-256 hash")]
+pub struct ParseError {
+    line: usize,
+    hash: String,
+}
+
+impl ResponseError for ParseError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Whether `hash` is a hex encoded SHA-256 hash, as produced by
+/// [`crate::db::content_hash`].
+pub fn is_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+pub fn parse(list: &str) -> Result<Vec<Entry>, ParseError> {
+    let mut entries = Vec::new();
+    for (index, line) in list.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (hash, reason) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let hash = hash.to_ascii_lowercase();
+        if !is_hash(&hash) {
+            return Err(ParseError {
+                line: index + 1,
+                hash,
+            });
+        }
+        entries.push(Entry {
+            hash,
+            reason: reason.trim().to_owned(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Formats `blocked` as a list that can be imported again.
+pub fn format(blocked: &[BlockedHash]) -> String {
+    blocked
+        .iter()
+        .map(|blocked| {
+            if blocked.reason.is_empty() {
+                format!("{}\n", blocked.hash)
+            } else {
+                // The reason has to stay on one line.
+                let reason = blocked.reason.split_whitespace().collect::<Vec<_>>();
+                format!("{} {}\n", blocked.hash, reason.join(" "))
+            }
+        })
+        .collect()
+}
+
+/// Adds `entries` to the blocklist, returns how many were not blocked yet.
+pub async fn import(entries: Vec<Entry>, database: &DB) -> Result<usize, bonsaidb::core::Error> {
+    let mut added = 0;
+    for Entry { hash, reason } in entries {
+        if database.block_hash(hash, reason).await? {
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+/// `pastemp blocklist`, `args` are the arguments following it.
+pub async fn command(args: &[String], database: &DB) -> Result<()> {
+    match args {
+        [command] if command == "list" => {
+            print!("{}", format(&database.blocked_hashes().await?));
+        }
+        [command, file] if command == "import" => {
+            let list = fs::read_to_string(file)
+                .with_context(|| format!("Reading the blocklist `{file}`"))?;
+            let added = import(parse(&list)?, database).await?;
+            println!("Blocked {added} new hashes");
+        }
+        [command, hash, reason @ ..] if command == "add" => {
+            let entries = parse(&format!("{hash} {}", reason.join(" ")))?;
+            if import(entries, database).await? == 0 {
+                println!("The hash was blocked already");
+            }
+        }
+        [command, hashes @ ..] if command == "remove" && !hashes.is_empty() => {
+            for hash in hashes {
+                if !database.unblock_hash(&hash.to_ascii_lowercase()).await? {
+                    println!("`{hash}` was not blocked");
+                }
+            }
+        }
+        _ => bail!("{USAGE}"),
+    }
+    Ok(())
+}
@@ -1,6 +1,10 @@
-use std::path::Path;
+use std::{
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use chrono::Duration;
 use figment::{
     providers::{Env, Format, Toml},
@@ -8,6 +12,9 @@ use figment::{
 };
 use serde::Deserialize;
 use serde_with::{serde_as, DurationSeconds};
+use url::Url;
+
+use crate::db::User;
 
 #[serde_as]
 #[derive(Deserialize)]
@@ -17,6 +24,32 @@ pub struct Config {
     #[serde_as(as = "DurationSeconds<i64>")]
     pub time_to_delete: Duration,
     pub base_url: String,
+    /// Theme used for highlighting with ANSI escape sequences.
+    pub terminal_theme: String,
+    /// Secret the database is encrypted with, see [`Config::encryption_secret`].
+    pub encryption_key: Option<String>,
+    /// File containing the secret, alternative to `encryption_key`.
+    pub encryption_key_file: Option<PathBuf>,
+    /// Secret the owner cookie is encrypted with, see [`crate::owner`].
+    pub cookie_key: Option<String>,
+    /// File a random secret is generated in, if `cookie_key` is not set.
+    pub cookie_key_file: PathBuf,
+    /// Previous values of `cookie_key`, still accepted to allow rotating it.
+    pub old_cookie_keys: Vec<String>,
+    /// Accept owner cookies from before they were encrypted.
+    pub plain_owner_cookies: bool,
+    /// Restrict cookies to HTTPS.
+    pub secure_cookies: bool,
+    /// Separate origin raw files are served from, so they can not access the
+    /// cookies of the main site.
+    pub raw_url: Option<Url>,
+    /// Allow anyone to create an account.
+    pub registration: bool,
+    /// Names of the accounts with additional privileges, like keeping pastes
+    /// forever.
+    pub admins: Vec<String>,
+    /// Reverse proxies whose forwarded headers name the client address.
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl Config {
@@ -28,4 +61,39 @@ impl Config {
             .extract()
             .context("Loading Config")
     }
+
+    /// Whether the owner id `owner` is the one of an account in `admins`.
+    pub fn is_admin(&self, owner: &str) -> bool {
+        User::name_from_owner(owner)
+            .is_some_and(|name| self.admins.iter().any(|admin| admin == name))
+    }
+
+    /// Whether `host`, as sent in the `Host` header, is the one of `raw_url`.
+    pub fn is_raw_host(&self, host: &str) -> bool {
+        self.raw_url.as_ref().is_some_and(|url| {
+            let Some(raw_host) = url.host_str() else {
+                return false;
+            };
+            match url.port() {
+                Some(port) => host.eq_ignore_ascii_case(&format!("{raw_host}:{port}")),
+                None => host.eq_ignore_ascii_case(raw_host),
+            }
+        })
+    }
+
+    /// Secret for encrypting the database at rest, if configured.
+    pub fn encryption_secret(&self) -> Result<Option<Vec<u8>>> {
+        match (&self.encryption_key, &self.encryption_key_file) {
+            (Some(_), Some(_)) => {
+                bail!("Only one of `encryption_key` and `encryption_key_file` can be set")
+            }
+            (Some(key), None) => Ok(Some(key.clone().into_bytes())),
+            (None, Some(path)) => {
+                let key = fs::read(path)
+                    .with_context(|| format!("Reading encryption key file `{}`", path.display()))?;
+                Ok(Some(key.trim_ascii_end().to_vec()))
+            }
+            (None, None) => Ok(None),
+        }
+    }
 }
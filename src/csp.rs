@@ -0,0 +1,72 @@
+//! Security headers, most importantly a `Content-Security-Policy` only
+//! allowing the scripts of our templates, marked with a per-request nonce.
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    web::Data,
+    Error,
+};
+use actix_web_lab::middleware::Next;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use rand::RngCore;
+
+use crate::config::Config;
+
+tokio::task_local! {
+    static NONCE: String;
+}
+
+/// Nonce of the current request, to be set on every `<script>` of the
+/// templates.
+pub fn nonce() -> String {
+    NONCE.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// Sets the security headers, handlers can set a stricter
+/// `Content-Security-Policy` themselves. The raw host only serves files, which
+/// the main site needs to be able to embed.
+pub async fn headers(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let config = request.app_data::<Data<Config>>().cloned();
+    let on_raw_host = config
+        .as_ref()
+        .is_some_and(|config| config.is_raw_host(request.connection_info().host()));
+    let raw_origin = config
+        .as_ref()
+        .and_then(|config| config.raw_url.as_ref())
+        .map(|url| format!(" {}", url.origin().ascii_serialization()))
+        .unwrap_or_default();
+
+    let mut nonce = [0; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let nonce = BASE64_STANDARD.encode(nonce);
+
+    let mut response = NONCE.scope(nonce.clone(), next.call(request)).await?;
+
+    let headers = response.headers_mut();
+    if !on_raw_host && !headers.contains_key(header::CONTENT_SECURITY_POLICY) {
+        headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_str(&format!(
+                "default-src 'self'{raw_origin}; script-src 'nonce-{nonce}'; \
+                 style-src 'self' 'unsafe-inline'; img-src 'self'{raw_origin} data:; \
+                 object-src 'self'{raw_origin}; base-uri 'none'; form-action 'self'; \
+                 frame-ancestors 'self'"
+            ))
+            .expect("nonce is valid in a header"),
+        );
+    }
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("no-referrer"),
+    );
+    Ok(response)
+}
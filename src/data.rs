@@ -0,0 +1,209 @@
+//! Viewers for structured data formats, JSON is rendered as a collapsible
+//! tree, CSV/TSV as a table, TOML and YAML are only validated.
+
+use std::fmt::Write;
+
+use askama::{Html, MarkupDisplay};
+use csv::{ReaderBuilder, StringRecord};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Successfully parsed data.
+pub enum View {
+    Json(String),
+    Table(Table),
+    /// Formats that are only validated, and displayed as code.
+    Valid,
+}
+
+/// Location and description of the first error in a file.
+pub struct ParseError {
+    pub message: String,
+    /// 1-based
+    pub line: usize,
+    /// 1-based
+    pub column: usize,
+    /// The line containing the error.
+    pub snippet: String,
+}
+
+impl ParseError {
+    fn new(source: &str, message: impl ToString, line: usize, column: usize) -> Self {
+        Self {
+            message: message.to_string(),
+            line,
+            column,
+            snippet: source
+                .lines()
+                .nth(line.saturating_sub(1))
+                .unwrap_or_default()
+                .to_owned(),
+        }
+    }
+
+    fn at_offset(source: &str, message: impl ToString, mut offset: usize) -> Self {
+        offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit_once('\n')
+            .map_or(before, |(_, line)| line)
+            .chars()
+            .count()
+            + 1;
+        Self::new(source, message, line, column)
+    }
+
+    /// Whitespace to place a marker below the erroneous column of `snippet`.
+    pub fn indent(&self) -> String {
+        self.snippet
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect()
+    }
+}
+
+fn escape(text: &str) -> MarkupDisplay<Html, &str> {
+    MarkupDisplay::new_unsafe(text, Html)
+}
+
+fn json_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str(r#"<span class="json-null">null</span>"#),
+        Value::Bool(value) => {
+            write!(out, r#"<span class="json-bool">{value}</span>"#).expect("writing to string")
+        }
+        Value::Number(value) => {
+            write!(out, r#"<span class="json-number">{value}</span>"#).expect("writing to string")
+        }
+        Value::String(value) => write!(
+            out,
+            r#"<span class="json-string">{}</span>"#,
+            escape(&Value::String(value.clone()).to_string())
+        )
+        .expect("writing to string"),
+        Value::Array(values) if values.is_empty() => out.push_str("[]"),
+        Value::Object(values) if values.is_empty() => out.push_str("{}"),
+        Value::Array(values) => {
+            write!(
+                out,
+                "<details open><summary>[<span class=\"json-count\">{} item{}</span>]</summary><ol start=\"0\">",
+                values.len(),
+                if values.len() == 1 { "" } else { "s" }
+            )
+            .expect("writing to string");
+            for value in values {
+                out.push_str("<li>");
+                json_value(out, value);
+                out.push_str("</li>");
+            }
+            out.push_str("</ol></details>");
+        }
+        Value::Object(values) => {
+            write!(
+                out,
+                "<details open><summary>{{<span class=\"json-count\">{} key{}</span>}}</summary><ul>",
+                values.len(),
+                if values.len() == 1 { "" } else { "s" }
+            )
+            .expect("writing to string");
+            for (key, value) in values {
+                write!(
+                    out,
+                    r#"<li><span class="json-key">{}</span>: "#,
+                    escape(&Value::String(key.clone()).to_string())
+                )
+                .expect("writing to string");
+                json_value(out, value);
+                out.push_str("</li>");
+            }
+            out.push_str("</ul></details>");
+        }
+    }
+}
+
+/// Renders `source` as a tree of collapsible `<details>`.
+pub fn json(source: &str) -> Result<String, ParseError> {
+    let value: Value = serde_json::from_str(source)
+        .map_err(|err| ParseError::new(source, &err, err.line(), err.column()))?;
+    let mut out = String::with_capacity(source.len() * 2);
+    out.push_str(r#"<div class="json">"#);
+    json_value(&mut out, &value);
+    out.push_str("</div>");
+    Ok(out)
+}
+
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Guesses the delimiter by choosing the candidate that splits the first lines
+/// into the same number of (more than one) columns.
+fn detect_delimiter(source: &str) -> u8 {
+    const CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+    CANDIDATES
+        .into_iter()
+        .filter_map(|delimiter| {
+            let mut reader = ReaderBuilder::new()
+                .delimiter(delimiter)
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(source.as_bytes());
+            let mut columns = None;
+            for record in reader.records().take(20) {
+                let len = record.ok()?.len();
+                if *columns.get_or_insert(len) != len {
+                    return None;
+                }
+            }
+            columns
+                .filter(|&columns| columns > 1)
+                .map(|columns| (columns, delimiter))
+        })
+        .max_by_key(|&(columns, _)| columns)
+        .map_or(b',', |(_, delimiter)| delimiter)
+}
+
+/// Parses `source` as CSV, the delimiter is detected unless `delimiter` is
+/// specified.
+pub fn table(source: &str, delimiter: Option<u8>) -> Result<Table, ParseError> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter.unwrap_or_else(|| detect_delimiter(source)))
+        .from_reader(source.as_bytes());
+    let to_error = |err: csv::Error| {
+        let line = err.position().map_or(1, |it| it.line() as usize);
+        ParseError::new(source, &err, line, 1)
+    };
+    let record = |record: StringRecord| record.iter().map(str::to_owned).collect();
+
+    let header = record(reader.headers().map_err(to_error)?.clone());
+    let rows = reader
+        .records()
+        .map(|it| it.map(record))
+        .collect::<Result<_, _>>()
+        .map_err(to_error)?;
+    Ok(Table { header, rows })
+}
+
+pub fn validate_toml(source: &str) -> Result<(), ParseError> {
+    source.parse::<toml::Table>().map(drop).map_err(|err| {
+        let offset = err.span().map_or(0, |it| it.start);
+        ParseError::at_offset(source, err.message(), offset)
+    })
+}
+
+pub fn validate_yaml(source: &str) -> Result<(), ParseError> {
+    // Validate all documents of a multi document stream.
+    for document in serde_yaml::Deserializer::from_str(source) {
+        serde_yaml::Value::deserialize(document).map_err(|err| {
+            let (line, column) = err.location().map_or((1, 1), |it| (it.line(), it.column()));
+            ParseError::new(source, &err, line, column)
+        })?;
+    }
+    Ok(())
+}
@@ -1,5 +1,16 @@
+use std::{fs, path::Path, time::SystemTime};
+
+use anyhow::{anyhow, bail, Context};
 use bonsaidb::{
-    core::schema::{self, Qualified},
+    core::{
+        connection::{AsyncLowLevelConnection, Range, Sort},
+        document::{CollectionDocument, Emit, KeyId},
+        schema::{
+            self, Collection, CollectionMapReduce, Qualified, SerializedCollection, SerializedView,
+            View, ViewMapResult, ViewSchema,
+        },
+        transaction::{Operation, Transaction},
+    },
     files::{
         direct::{self, Async},
         FileConfig, FilesSchema, Truncate,
@@ -7,18 +18,80 @@ use bonsaidb::{
     local::{config::Builder, AsyncDatabase},
 };
 use chrono::{Duration, Utc};
-use rand::distributions::DistString;
+use rand::distributions::{Alphanumeric, DistString};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use crate::{util::ReadableAlphanumeric, StorageConfiguration, RESERVED_URLS};
+use crate::{
+    util::ReadableAlphanumeric,
+    vault::{self, SealedKeyStorage},
+    StorageConfiguration, RESERVED_URLS,
+};
 
 type Result<T = ()> = std::result::Result<T, bonsaidb::core::Error>;
 pub type DateTime = chrono::DateTime<Utc>;
 
+/// Directory generated thumbnails are cached in, named like their paste.
+const THUMBNAILS: &str = "/thumb/";
+const REVISIONS: &str = "/rev/";
+
+const PATH: &str = "data.bonsaidb";
+/// Encrypted copy created by [`DB::encrypt`], replacing [`PATH`] when complete.
+const ENCRYPTING_PATH: &str = "data.bonsaidb.encrypting";
+const PLAIN_PATH: &str = "data.bonsaidb.plain";
+/// Documents copied per transaction by [`DB::encrypt`].
+const BATCH: u32 = 64;
+
+/// Prefix of [`Metadata::owner`] for pastes uploaded by a [`User`].
+const USER_OWNER: &str = "user:";
+/// Prefix of API keys, making them recognizable e.g. for secret scanners.
+const API_KEY_PREFIX: &str = "pastemp_";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Metadata {
     pub delete_at: Option<DateTime>,
     pub owner: String,
+    /// Names of the files of a multi-file paste, stored in a directory named
+    /// like the paste. Empty for single files.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Times at which the revisions following the first were submitted, older
+    /// revisions are stored in a directory named like the paste below
+    /// [`REVISIONS`].
+    #[serde(default)]
+    pub edits: Vec<DateTime>,
+    /// File name of the paste this one was forked from.
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// Argon2 hash of the password required to view the paste.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Contents are encrypted by the client, see [`crate::encrypted`].
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Extension the paste was uploaded with.
+    #[serde(default)]
+    pub extension: Option<String>,
+    /// Pending request to delete the paste by someone other than the owner.
+    #[serde(default)]
+    pub deletion_request: Option<DeletionRequest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeletionRequest {
+    /// Owner id of the requester, if they had one.
+    pub requested_by: Option<String>,
+    pub requested_at: DateTime,
+    /// [`Metadata::delete_at`] before the request, restored when the owner
+    /// cancels it.
+    pub previous: Option<DateTime>,
+}
+
+impl DeletionRequest {
+    /// Name of the account that requested the deletion.
+    pub fn requester(&self) -> Option<&str> {
+        User::name_from_owner(self.requested_by.as_deref()?)
+    }
 }
 
 pub struct Files;
@@ -36,24 +109,318 @@ impl FileConfig for Files {
     }
 }
 
-type File = direct::File<Async<AsyncDatabase>, Files>;
+pub type File = direct::File<Async<AsyncDatabase>, Files>;
+
+/// Local account, identified by its name.
+#[derive(Serialize, Deserialize, Debug, Clone, Collection)]
+#[collection(name = "users", primary_key = String)]
+pub struct User {
+    #[natural_id]
+    pub name: String,
+    /// Argon2 hash of the password.
+    pub password: String,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyInfo>,
+}
+
+impl User {
+    /// [`Metadata::owner`] of the pastes uploaded by the user `name`.
+    pub fn owner(name: &str) -> String {
+        format!("{USER_OWNER}{name}")
+    }
+
+    /// Name of the user owning pastes with [`Metadata::owner`] `owner`.
+    pub fn name_from_owner(owner: &str) -> Option<&str> {
+        owner.strip_prefix(USER_OWNER)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKeyInfo {
+    /// Id of the [`ApiKey`].
+    pub id: String,
+    pub label: String,
+    pub created: DateTime,
+}
+
+/// API key of a user, identified by the SHA-256 hash of the key. The keys are
+/// random, so unlike passwords they don't need a slow hash.
+#[derive(Serialize, Deserialize, Debug, Clone, Collection)]
+#[collection(name = "api-keys", primary_key = String)]
+struct ApiKey {
+    #[natural_id]
+    id: String,
+    user: String,
+}
+
+fn api_key_id(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Report of a paste by a visitor, e.g. for abuse or copyright infringement.
+#[derive(Serialize, Deserialize, Debug, Clone, Collection)]
+#[collection(name = "reports")]
+pub struct Report {
+    pub paste: String,
+    pub reason: String,
+    /// Keyed hash of the reporter's IP address, to recognize repeated reports
+    /// without storing the address.
+    pub reporter: String,
+    pub created: DateTime,
+}
+
+/// SHA-256 hash of content that must not be uploaded again.
+#[derive(Serialize, Deserialize, Debug, Clone, Collection)]
+#[collection(name = "blocklist", primary_key = String)]
+pub struct BlockedHash {
+    #[natural_id]
+    pub hash: String,
+    pub reason: String,
+    pub blocked_at: DateTime,
+}
+
+/// Hex encoded SHA-256 hash of `data`, as stored in [`BlockedHash`].
+pub fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Owner of a paste, as the files collection of `bonsaidb-files` can not have
+/// views of its own. Entries of deleted pastes are removed when listing them.
+#[derive(Serialize, Deserialize, Debug, Clone, Collection)]
+#[collection(name = "owners", primary_key = String, views = [ByOwner])]
+struct PasteOwner {
+    #[natural_id]
+    name: String,
+    owner: String,
+}
+
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = PasteOwner, key = String, value = (), name = "by-owner")]
+struct ByOwner;
+
+impl CollectionMapReduce for ByOwner {
+    fn map<'doc>(&self, document: CollectionDocument<PasteOwner>) -> ViewMapResult<'doc, Self> {
+        document.header.emit_key(document.contents.owner)
+    }
+}
+
+/// Summary of a paste for its owner.
+#[derive(Serialize, Debug)]
+pub struct PasteInfo {
+    pub name: String,
+    pub extension: Option<String>,
+    pub created: Option<DateTime>,
+    /// Size in bytes, of all files for multi-file pastes.
+    pub size: u64,
+    pub files: usize,
+    pub delete_at: Option<DateTime>,
+    pub encrypted: bool,
+    pub password: bool,
+}
 
 #[derive(Debug, schema::Schema)]
-#[schema(name = "paste", include=[FilesSchema<Files>])]
+#[schema(
+    name = "paste",
+    include=[FilesSchema<Files>],
+    collections=[User, ApiKey, PasteOwner, Report, BlockedHash]
+)]
 struct Schema;
 
 pub struct DB(AsyncDatabase);
 
+fn configuration(path: &str, secret: Option<&[u8]>) -> anyhow::Result<StorageConfiguration> {
+    let configuration = StorageConfiguration::new(path);
+    let Some(secret) = secret else {
+        return Ok(configuration);
+    };
+    let key_storage = SealedKeyStorage::new(path, secret)
+        .map_err(|e| anyhow!("Deriving the encryption key: {e}"))?;
+    Ok(configuration
+        .vault_key_storage(key_storage)
+        .default_encryption_key(KeyId::Master))
+}
+
+/// Completes replacing the plain database in [`DB::encrypt`] after it was
+/// interrupted. The plain database is only moved to [`PLAIN_PATH`] once the
+/// encrypted copy is complete.
+fn finish_encryption() -> anyhow::Result<()> {
+    if !Path::new(PLAIN_PATH).exists() {
+        return Ok(());
+    }
+    if !Path::new(PATH).exists() {
+        if !Path::new(ENCRYPTING_PATH).exists() {
+            bail!("The database `{PATH}` is missing, the plain database is left at `{PLAIN_PATH}`");
+        }
+        fs::rename(ENCRYPTING_PATH, PATH).context("Moving the encrypted database into place")?;
+    }
+    fs::remove_dir_all(PLAIN_PATH).context("Removing the plain database")?;
+    Ok(())
+}
+
 impl DB {
-    pub async fn new() -> Result<Self> {
-        Ok(Self(
-            AsyncDatabase::open::<Schema>(StorageConfiguration::new("data.bonsaidb")).await?,
-        ))
+    /// Opens the database, encrypted at rest when a `secret` is configured.
+    pub async fn new(secret: Option<&[u8]>) -> anyhow::Result<Self> {
+        finish_encryption()?;
+        if !Path::new(PATH).exists() && Path::new(ENCRYPTING_PATH).exists() {
+            bail!(
+                "The database `{PATH}` is missing, but `{ENCRYPTING_PATH}` is left from an interrupted `pastemp encrypt-database`"
+            );
+        }
+        let sealed = Path::new(PATH).join(vault::DIRECTORY).exists();
+        match secret {
+            Some(_) if Path::new(PATH).exists() && !sealed => bail!(
+                "The database `{PATH}` is not encrypted, run `pastemp encrypt-database` to encrypt it"
+            ),
+            None if sealed => bail!(
+                "The database `{PATH}` is encrypted, but neither `encryption_key` nor `encryption_key_file` is configured"
+            ),
+            _ => {}
+        }
+        let database = Self(
+            AsyncDatabase::open::<Schema>(configuration(PATH, secret)?)
+                .await
+                .context("Opening the database")?,
+        );
+        database
+            .index_owners()
+            .await
+            .context("Indexing the owners of pastes")?;
+        Ok(database)
     }
 
+    /// Adds the pastes uploaded before [`PasteOwner`] existed to it.
+    async fn index_owners(&self) -> Result {
+        if PasteOwner::all_async(&self.0).count().await? > 0 {
+            return Ok(());
+        }
+        for file in Files::list_async("/", &self.0).await? {
+            self.index_owner(file.name(), &file.metadata().owner)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn index_owner(&self, name: &str, owner: &str) -> Result {
+        PasteOwner {
+            name: name.to_owned(),
+            owner: owner.to_owned(),
+        }
+        .overwrite_into_async(&name, &self.0)
+        .await?;
+        Ok(())
+    }
+
+    /// Encrypts an existing plain database, by copying all documents into a
+    /// new encrypted database that then replaces the plain one.
+    pub async fn encrypt(secret: &[u8]) -> anyhow::Result<()> {
+        finish_encryption()?;
+        if !Path::new(PATH).exists() {
+            bail!("There is no database at `{PATH}`");
+        }
+        if Path::new(PATH).join(vault::DIRECTORY).exists() {
+            bail!("The database `{PATH}` is already encrypted");
+        }
+        if Path::new(ENCRYPTING_PATH).exists() {
+            fs::remove_dir_all(ENCRYPTING_PATH)
+                .context("Removing the leftovers of a previous attempt")?;
+        }
+        {
+            let plain = AsyncDatabase::open::<Schema>(StorageConfiguration::new(PATH)).await?;
+            let encrypted =
+                AsyncDatabase::open::<Schema>(configuration(ENCRYPTING_PATH, Some(secret))?)
+                    .await?;
+            for collection in [
+                Files::files_name(),
+                Files::blocks_name(),
+                User::collection_name(),
+                ApiKey::collection_name(),
+                PasteOwner::collection_name(),
+                Report::collection_name(),
+                BlockedHash::collection_name(),
+            ] {
+                let mut ids = Range::from(..);
+                loop {
+                    let documents = plain
+                        .list_from_collection(ids, Sort::Ascending, Some(BATCH), &collection)
+                        .await?;
+                    let Some(last) = documents.last() else {
+                        break;
+                    };
+                    ids = Range::from(..).after(last.header.id.clone());
+                    let mut transaction = Transaction::new();
+                    for document in documents {
+                        transaction.push(Operation::insert(
+                            collection.clone(),
+                            Some(document.header.id),
+                            document.contents,
+                        ));
+                    }
+                    encrypted.apply_transaction(transaction).await?;
+                }
+            }
+        }
+        fs::rename(PATH, PLAIN_PATH)?;
+        fs::rename(ENCRYPTING_PATH, PATH)?;
+        fs::remove_dir_all(PLAIN_PATH).context("Removing the plain database")?;
+        Ok(())
+    }
+
+    /// Deletes `name` at `delete_at`, unless it is deleted earlier anyway.
+    /// This replaces a pending deletion request.
     pub async fn delete_at(&self, name: &str, delete_at: DateTime) -> Result {
         if let Some(mut file) = Files::load_async(name, &self.0).await? {
-            file.metadata_mut().delete_at = Some(delete_at);
+            let metadata = file.metadata_mut();
+            metadata.deletion_request = None;
+            metadata.delete_at = Some(metadata.delete_at.map_or(delete_at, |it| it.min(delete_at)));
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    /// [`DB::delete_at`] on behalf of someone other than the owner, recording
+    /// the request so the owner can cancel it with [`DB::cancel_deletion`].
+    pub async fn request_deletion(
+        &self,
+        name: &str,
+        requested_by: Option<String>,
+        delete_at: DateTime,
+    ) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            let metadata = file.metadata_mut();
+            // Later requests keep the expiry from before the first one.
+            if metadata.deletion_request.is_none() {
+                metadata.deletion_request = Some(DeletionRequest {
+                    requested_by,
+                    requested_at: Utc::now(),
+                    previous: metadata.delete_at,
+                });
+            }
+            metadata.delete_at = Some(metadata.delete_at.map_or(delete_at, |it| it.min(delete_at)));
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    /// Restores the expiry from before the pending deletion request.
+    pub async fn cancel_deletion(&self, name: &str) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            let metadata = file.metadata_mut();
+            if let Some(request) = metadata.deletion_request.take() {
+                metadata.delete_at = request.previous;
+                file.update_metadata().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets when `name` is deleted, unlike [`DB::delete_at`] it can also
+    /// postpone the deletion or make the paste permanent with `None`. This
+    /// replaces a pending deletion request.
+    pub async fn set_expiry(&self, name: &str, delete_at: Option<DateTime>) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            let metadata = file.metadata_mut();
+            metadata.delete_at = delete_at;
+            metadata.deletion_request = None;
             file.update_metadata().await?;
         }
         Ok(())
@@ -70,6 +437,7 @@ impl DB {
             if let Some(delete_at) = file.metadata().delete_at {
                 if Utc::now() > delete_at {
                     file.delete().await?;
+                    self.delete_derived(name).await?;
                     return Ok(None);
                 }
             }
@@ -79,15 +447,28 @@ impl DB {
         }
     }
 
-    pub async fn new_file(&self, owner: String, ttl: Option<Duration>) -> Result<File> {
+    /// Creates an empty paste, protected by the password `hash` from the start.
+    pub async fn new_file(
+        &self,
+        owner: String,
+        ttl: Option<Duration>,
+        hash: Option<String>,
+    ) -> Result<File> {
         let mut tries = 0;
         // TODO auto increase
         let length = 4;
         let metadata = Metadata {
             delete_at: ttl.map(|ttl| Utc::now() + ttl),
             owner,
+            files: Vec::new(),
+            edits: Vec::new(),
+            parent: None,
+            password: hash,
+            encrypted: false,
+            extension: None,
+            deletion_request: None,
         };
-        Ok(loop {
+        let file = loop {
             let name = loop {
                 let id = ReadableAlphanumeric.sample_string(&mut rand::thread_rng(), length);
                 if !RESERVED_URLS.contains(&id.as_str()) {
@@ -112,6 +493,7 @@ impl DB {
                     .await?;
                     *file.metadata_mut() = metadata;
                     file.truncate(0, Truncate::RemovingStart).await?;
+                    self.delete_derived(&name).await?;
                     break file;
                 }
                 Err(bonsaidb::files::Error::Database(
@@ -119,6 +501,463 @@ impl DB {
                 )) => continue,
                 Err(err) => return Err(err.into()),
             }
+        };
+        self.index_owner(file.name(), &file.metadata().owner)
+            .await?;
+        Ok(file)
+    }
+
+    /// Pastes of `owner`, the newest first.
+    pub async fn owned_pastes(&self, owner: &str) -> Result<Vec<PasteInfo>> {
+        let mut pastes = Vec::new();
+        for mapping in ByOwner::entries_async(&self.0)
+            .with_key(owner)
+            .query()
+            .await?
+        {
+            let name = mapping.source.id;
+            let Some(mut file) = self.load_file(&name).await? else {
+                if let Some(owner) = PasteOwner::get_async(&name, &self.0).await? {
+                    owner.delete_async(&self.0).await?;
+                }
+                continue;
+            };
+            // The name might have been reused by another owner.
+            if file.metadata().owner != owner {
+                continue;
+            }
+            let mut size = file.len().await?;
+            for file_name in &file.metadata().files {
+                if let Some(mut file) =
+                    Files::load_async(&format!("/{name}/{file_name}"), &self.0).await?
+                {
+                    size += file.len().await?;
+                }
+            }
+            let metadata = file.metadata();
+            pastes.push(PasteInfo {
+                extension: metadata.extension.clone(),
+                created: SystemTime::try_from(file.created_at())
+                    .ok()
+                    .map(DateTime::from),
+                size,
+                files: metadata.files.len(),
+                delete_at: metadata.delete_at,
+                encrypted: metadata.encrypted,
+                password: metadata.password.is_some(),
+                name,
+            });
+        }
+        pastes.sort_by_key(|paste| std::cmp::Reverse(paste.created));
+        Ok(pastes)
+    }
+
+    /// Makes the pastes of `from` belong to `to`, e.g. the pastes uploaded
+    /// anonymously before logging in.
+    pub async fn transfer_pastes(&self, from: &str, to: &str) -> Result {
+        for mapping in ByOwner::entries_async(&self.0)
+            .with_key(from)
+            .query()
+            .await?
+        {
+            let name = mapping.source.id;
+            let Some(mut file) = self.load_file(&name).await? else {
+                continue;
+            };
+            if file.metadata().owner == from {
+                file.metadata_mut().owner = to.to_owned();
+                file.update_metadata().await?;
+                self.index_owner(&name, to).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the single file paste `name` into a multi-file paste, with its
+    /// current contents becoming the file `file_name`.
+    pub async fn make_group(&self, name: &str, file_name: &str) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            let metadata = file.metadata().clone();
+            file.metadata_mut().delete_at = None;
+            file.update_metadata().await?;
+            file.move_to(&format!("/{name}/{file_name}")).await?;
+            Files::build_with_metadata(name, metadata)
+                .create_async(&self.0)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Creates the file `file_name` belonging to the multi-file paste `name`,
+    /// it is only listed once added with [`DB::set_group_files`].
+    pub async fn new_group_file(&self, name: &str, file_name: &str) -> Result<File> {
+        let owner = self.file_owner(name).await?.unwrap_or_default();
+        Ok(Files::build_with_metadata(
+            format!("/{name}/{file_name}"),
+            Metadata {
+                delete_at: None,
+                owner,
+                files: Vec::new(),
+                edits: Vec::new(),
+                parent: None,
+                password: None,
+                encrypted: false,
+                extension: None,
+                deletion_request: None,
+            },
+        )
+        .create_async(&self.0)
+        .await?)
+    }
+
+    pub async fn set_parent(&self, name: &str, parent: String) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            file.metadata_mut().parent = Some(parent);
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn user(&self, name: &str) -> Result<Option<User>> {
+        Ok(User::get_async(&name, &self.0)
+            .await?
+            .map(|user| user.contents))
+    }
+
+    /// Creates the user `name`, returns `false` if the name is taken.
+    pub async fn create_user(&self, name: &str, password: String) -> Result<bool> {
+        let user = User {
+            name: name.to_owned(),
+            password,
+            api_keys: Vec::new(),
+        };
+        match user.push_into_async(&self.0).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.error.conflicting_document::<User>().is_some() => Ok(false),
+            Err(e) => Err(e.error),
+        }
+    }
+
+    /// Creates an API key for the user `name` and returns it, it can not be
+    /// retrieved later.
+    pub async fn create_api_key(&self, name: &str, label: String) -> Result<Option<String>> {
+        let Some(mut user) = User::get_async(&name, &self.0).await? else {
+            return Ok(None);
+        };
+        let key =
+            API_KEY_PREFIX.to_owned() + &Alphanumeric.sample_string(&mut rand::thread_rng(), 40);
+        let id = api_key_id(&key);
+        ApiKey {
+            id: id.clone(),
+            user: name.to_owned(),
+        }
+        .push_into_async(&self.0)
+        .await?;
+        user.contents.api_keys.push(ApiKeyInfo {
+            id,
+            label,
+            created: Utc::now(),
+        });
+        user.update_async(&self.0).await?;
+        Ok(Some(key))
+    }
+
+    /// Name of the user the API key `key` belongs to.
+    pub async fn api_key_user(&self, key: &str) -> Result<Option<String>> {
+        Ok(ApiKey::get_async(&api_key_id(key), &self.0)
+            .await?
+            .map(|key| key.contents.user))
+    }
+
+    pub async fn revoke_api_key(&self, name: &str, id: &str) -> Result {
+        if let Some(key) = ApiKey::get_async(&id, &self.0)
+            .await?
+            .filter(|key| key.contents.user == name)
+        {
+            key.delete_async(&self.0).await?;
+        }
+        if let Some(mut user) = User::get_async(&name, &self.0).await? {
+            user.contents.api_keys.retain(|key| key.id != id);
+            user.update_async(&self.0).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn add_report(&self, paste: &str, reason: String, reporter: String) -> Result {
+        Report {
+            paste: paste.to_owned(),
+            reason,
+            reporter,
+            created: Utc::now(),
+        }
+        .push_into_async(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    /// All reports with their ids, the oldest first.
+    pub async fn reports(&self) -> Result<Vec<(u64, Report)>> {
+        Ok(Report::all_async(&self.0)
+            .await?
+            .into_iter()
+            .map(|report| (report.header.id, report.contents))
+            .collect())
+    }
+
+    pub async fn report(&self, id: u64) -> Result<Option<Report>> {
+        Ok(Report::get_async(&id, &self.0)
+            .await?
+            .map(|report| report.contents))
+    }
+
+    pub async fn delete_report(&self, id: u64) -> Result {
+        if let Some(report) = Report::get_async(&id, &self.0).await? {
+            report.delete_async(&self.0).await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes all reports of the paste `name`, once it was taken care of.
+    pub async fn delete_reports_of(&self, name: &str) -> Result {
+        for report in Report::all_async(&self.0).await? {
+            if report.contents.paste == name {
+                report.delete_async(&self.0).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Hashes of the contents of `name`, of all files for multi-file pastes.
+    pub async fn content_hashes(&self, name: &str) -> Result<Vec<String>> {
+        let Some(file) = self.load_file(name).await? else {
+            return Ok(Vec::new());
+        };
+        let mut hashes = Vec::new();
+        for file_name in &file.metadata().files {
+            if let Some(file) = self.load_group_file(name, file_name).await? {
+                hashes.push(content_hash(&read(&file).await?));
+            }
+        }
+        if file.metadata().files.is_empty() {
+            hashes.push(content_hash(&read(&file).await?));
+        }
+        Ok(hashes)
+    }
+
+    /// Adds `hash` to the blocklist, keeping the reason of an existing entry.
+    /// Returns `false` if it was blocked already.
+    pub async fn block_hash(&self, hash: String, reason: String) -> Result<bool> {
+        match (BlockedHash {
+            hash,
+            reason,
+            blocked_at: Utc::now(),
         })
+        .push_into_async(&self.0)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if e.error.conflicting_document::<BlockedHash>().is_some() => Ok(false),
+            Err(e) => Err(e.error),
+        }
     }
+
+    /// Removes `hash` from the blocklist, returns `false` if it was not on it.
+    pub async fn unblock_hash(&self, hash: &str) -> Result<bool> {
+        let Some(blocked) = BlockedHash::get_async(&hash, &self.0).await? else {
+            return Ok(false);
+        };
+        blocked.delete_async(&self.0).await?;
+        Ok(true)
+    }
+
+    pub async fn is_blocked(&self, hash: &str) -> Result<bool> {
+        Ok(BlockedHash::get_async(&hash, &self.0).await?.is_some())
+    }
+
+    pub async fn blocked_hashes(&self) -> Result<Vec<BlockedHash>> {
+        Ok(BlockedHash::all_async(&self.0)
+            .await?
+            .into_iter()
+            .map(|blocked| blocked.contents)
+            .collect())
+    }
+
+    pub async fn set_password(&self, name: &str, hash: Option<String>) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            file.metadata_mut().password = hash;
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_extension(&self, name: &str, extension: String) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            file.metadata_mut().extension = Some(extension);
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_encrypted(&self, name: &str) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            file.metadata_mut().encrypted = true;
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_group_files(&self, name: &str, files: Vec<String>) -> Result {
+        if let Some(mut file) = Files::load_async(name, &self.0).await? {
+            file.metadata_mut().files = files;
+            file.update_metadata().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn load_group_file(&self, name: &str, file_name: &str) -> Result<Option<File>> {
+        if self.load_file(name).await?.is_some() {
+            Ok(Files::load_async(&format!("/{name}/{file_name}"), &self.0).await?)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Stores the current contents of `name` as a previous revision and
+    /// replaces them with `data`, returns the number of the new revision.
+    pub async fn new_revision(&self, name: &str, data: &[u8]) -> Result<Option<usize>> {
+        let Some(mut file) = self.load_file(name).await? else {
+            return Ok(None);
+        };
+        let revision = file.metadata().edits.len() + 1;
+        let previous = file
+            .contents()
+            .await?
+            .into_vec()
+            .await
+            .map_err(|err| bonsaidb::core::Error::other("files", err))?;
+        Files::build_with_metadata(
+            format!("{REVISIONS}{name}/{revision}"),
+            Metadata {
+                delete_at: None,
+                owner: file.metadata().owner.clone(),
+                files: Vec::new(),
+                edits: Vec::new(),
+                parent: None,
+                password: None,
+                encrypted: false,
+                extension: None,
+                deletion_request: None,
+            },
+        )
+        .contents(&previous)
+        .create_async(&self.0)
+        .await?;
+        file.truncate(0, Truncate::RemovingStart).await?;
+        file.append(data).await?;
+        file.metadata_mut().edits.push(Utc::now());
+        file.update_metadata().await?;
+        self.delete_thumbnail(name).await?;
+        Ok(Some(revision + 1))
+    }
+
+    /// Loads the 1-based `revision` of `name`.
+    pub async fn load_revision(&self, name: &str, revision: usize) -> Result<Option<File>> {
+        let Some(file) = self.load_file(name).await? else {
+            return Ok(None);
+        };
+        let current = file.metadata().edits.len() + 1;
+        if revision == current {
+            Ok(Some(file))
+        } else if (1..current).contains(&revision) {
+            Ok(Files::load_async(&format!("{REVISIONS}{name}/{revision}"), &self.0).await?)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Deletes `name` and the files belonging to it right away.
+    pub async fn delete(&self, name: &str) -> Result {
+        if let Some(file) = Files::load_async(name, &self.0).await? {
+            file.delete().await?;
+        }
+        self.delete_derived(name).await
+    }
+
+    /// Deletes the files belonging to the paste `name`.
+    async fn delete_derived(&self, name: &str) -> Result {
+        self.delete_thumbnail(name).await?;
+        if let Some(owner) = PasteOwner::get_async(&name, &self.0).await? {
+            owner.delete_async(&self.0).await?;
+        }
+        for directory in [format!("/{name}/"), format!("{REVISIONS}{name}/")] {
+            for file in Files::list_async(&directory, &self.0).await? {
+                file.delete().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the contents of the file at `path` with `data`.
+    pub async fn replace_contents(&self, path: &str, data: &[u8]) -> Result {
+        if let Some(file) = Files::load_async(path, &self.0).await? {
+            file.truncate(0, Truncate::RemovingStart).await?;
+            file.append(data).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn load_thumbnail(&self, name: &str) -> Result<Option<File>> {
+        Ok(Files::load_async(&format!("{THUMBNAILS}{name}"), &self.0).await?)
+    }
+
+    pub async fn store_thumbnail(&self, name: &str, data: &[u8]) -> Result {
+        let metadata = Metadata {
+            delete_at: None,
+            owner: String::new(),
+            files: Vec::new(),
+            edits: Vec::new(),
+            parent: None,
+            password: None,
+            encrypted: false,
+            extension: None,
+            deletion_request: None,
+        };
+        match Files::build_with_metadata(format!("{THUMBNAILS}{name}"), metadata)
+            .contents(data)
+            .create_async(&self.0)
+            .await
+        {
+            // Generated concurrently by another request.
+            Ok(_)
+            | Err(bonsaidb::files::Error::Database(bonsaidb::core::Error::UniqueKeyViolation {
+                ..
+            })) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete_thumbnail(&self, name: &str) -> Result {
+        if let Some(file) = Files::load_async(&format!("{THUMBNAILS}{name}"), &self.0).await? {
+            file.delete().await?;
+        }
+        Ok(())
+    }
+}
+
+async fn read(file: &File) -> Result<Vec<u8>> {
+    file.contents()
+        .await?
+        .into_vec()
+        .await
+        .map_err(|err| bonsaidb::core::Error::other("files", err))
+}
+
+/// Creation times of all revisions of `file`, the last one being the current.
+pub fn revision_times(file: &File) -> Vec<DateTime> {
+    SystemTime::try_from(file.created_at())
+        .ok()
+        .map(DateTime::from)
+        .into_iter()
+        .chain(file.metadata().edits.iter().copied())
+        .collect()
 }
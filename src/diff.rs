@@ -0,0 +1,742 @@
+//! Line based diffs of two texts, rendered with syntax highlighting and
+//! markers for the changed words inside modified lines.
+
+use std::{ops::Range, time::Duration};
+
+use similar::{ChangeTag, DiffTag, TextDiff};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+use crate::highlight::LineHighlighter;
+
+/// Lines of unchanged context around changes.
+const CONTEXT: usize = 3;
+/// Time after which diffing falls back to a less minimal result.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Context,
+    Added,
+    Removed,
+}
+
+impl Kind {
+    pub fn class(&self) -> &'static str {
+        match self {
+            Kind::Context => "context",
+            Kind::Added => "added",
+            Kind::Removed => "removed",
+        }
+    }
+}
+
+pub struct Line {
+    pub kind: Kind,
+    /// 1-based line number in the old file.
+    pub old: Option<usize>,
+    /// 1-based line number in the new file.
+    pub new: Option<usize>,
+    pub html: String,
+}
+
+pub struct Hunk {
+    pub header: String,
+    pub lines: Vec<Line>,
+}
+
+impl Hunk {
+    /// Pairs of old and new lines for a side-by-side view, removed lines are
+    /// shown next to the lines added in their place.
+    pub fn rows(&self) -> Vec<(Option<&Line>, Option<&Line>)> {
+        let mut rows = Vec::with_capacity(self.lines.len());
+        let mut lines = self.lines.iter().peekable();
+        while let Some(line) = lines.next() {
+            if line.kind == Kind::Context {
+                rows.push((Some(line), Some(line)));
+                continue;
+            }
+            let (mut removed, mut added) = (Vec::new(), Vec::new());
+            let mut line = Some(line);
+            while let Some(current) = line {
+                if current.kind == Kind::Removed {
+                    removed.push(current);
+                } else {
+                    added.push(current);
+                }
+                // Removed lines following added ones start a new block.
+                line = lines.next_if(|it| {
+                    it.kind == Kind::Added || it.kind == Kind::Removed && added.is_empty()
+                });
+            }
+            for i in 0..removed.len().max(added.len()) {
+                rows.push((removed.get(i).copied(), added.get(i).copied()));
+            }
+        }
+        rows
+    }
+}
+
+pub struct FileDiff {
+    pub old_name: String,
+    pub new_name: String,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    pub fn name(&self) -> &str {
+        if self.new_name == "/dev/null" {
+            &self.old_name
+        } else {
+            &self.new_name
+        }
+    }
+
+    fn count(&self, kind: Kind) -> usize {
+        self.hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.kind == kind)
+            .count()
+    }
+
+    pub fn added(&self) -> usize {
+        self.count(Kind::Added)
+    }
+
+    pub fn removed(&self) -> usize {
+        self.count(Kind::Removed)
+    }
+}
+
+/// A line of a hunk before highlighting.
+pub(crate) struct RawLine<'a> {
+    pub kind: Kind,
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+    pub text: &'a str,
+}
+
+/// Byte ranges of the words that differ between `old` and `new`, nothing is
+/// marked if the lines are too different for markers to be helpful.
+fn word_marks(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let diff = TextDiff::configure().timeout(TIMEOUT).diff_words(old, new);
+    if diff.ratio() < 0.5 {
+        return Default::default();
+    }
+    let offsets = |slices: &[&str]| {
+        let mut offsets = vec![0];
+        for slice in slices {
+            offsets.push(offsets.last().expect("starts with 0") + slice.len());
+        }
+        offsets
+    };
+    let (old_offsets, new_offsets) = (offsets(diff.old_slices()), offsets(diff.new_slices()));
+    let (mut old_marks, mut new_marks) = (Vec::new(), Vec::new());
+    for op in diff.ops() {
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        if matches!(op.tag(), DiffTag::Delete | DiffTag::Replace) {
+            old_marks.push(old_offsets[old_range.start]..old_offsets[old_range.end]);
+        }
+        if matches!(op.tag(), DiffTag::Insert | DiffTag::Replace) {
+            new_marks.push(new_offsets[new_range.start]..new_offsets[new_range.end]);
+        }
+    }
+    (old_marks, new_marks)
+}
+
+/// Highlights the lines of a hunk, lines removed directly before added ones
+/// get markers for the changed words.
+pub(crate) fn render(
+    lines: &[RawLine],
+    old: &mut LineHighlighter,
+    new: &mut LineHighlighter,
+) -> Vec<Line> {
+    let mut marks = vec![Vec::new(); lines.len()];
+    let mut i = 0;
+    while i < lines.len() {
+        let removed = lines[i..]
+            .iter()
+            .take_while(|it| it.kind == Kind::Removed)
+            .count();
+        let added = lines[i + removed..]
+            .iter()
+            .take_while(|it| it.kind == Kind::Added)
+            .count();
+        for j in 0..removed.min(added) {
+            let (old, new) = word_marks(lines[i + j].text, lines[i + removed + j].text);
+            marks[i + j] = old;
+            marks[i + removed + j] = new;
+        }
+        i += (removed + added).max(1);
+    }
+
+    lines
+        .iter()
+        .zip(marks)
+        .map(|(line, marks)| Line {
+            kind: line.kind,
+            old: line.old,
+            new: line.new,
+            html: match line.kind {
+                Kind::Context => {
+                    old.skip(line.text);
+                    new.line(line.text, &[])
+                }
+                Kind::Added => new.line(line.text, &marks),
+                Kind::Removed => old.line(line.text, &marks),
+            },
+        })
+        .collect()
+}
+
+fn trim_newline(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+/// Diffs `old` against `new` line by line.
+pub fn texts(
+    (old_name, old): (&str, &str),
+    (new_name, new): (&str, &str),
+    syntax: Option<&SyntaxReference>,
+    syntaxes: &SyntaxSet,
+) -> FileDiff {
+    let diff = TextDiff::configure().timeout(TIMEOUT).diff_lines(old, new);
+    let mut old_highlighter = LineHighlighter::new(syntax, syntaxes);
+    let mut new_highlighter = LineHighlighter::new(syntax, syntaxes);
+    // Lines already passed to the highlighters.
+    let (mut old_line, mut new_line) = (0, 0);
+
+    let hunks = diff
+        .grouped_ops(CONTEXT)
+        .into_iter()
+        .map(|ops| {
+            let (first, last) = (&ops[0], &ops[ops.len() - 1]);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+
+            // Keep the parse state in sync with the skipped lines.
+            for line in &diff.old_slices()[old_line..old_range.start] {
+                old_highlighter.skip(trim_newline(line));
+            }
+            for line in &diff.new_slices()[new_line..new_range.start] {
+                new_highlighter.skip(trim_newline(line));
+            }
+            (old_line, new_line) = (old_range.end, new_range.end);
+
+            let lines: Vec<_> = ops
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| RawLine {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => Kind::Context,
+                        ChangeTag::Insert => Kind::Added,
+                        ChangeTag::Delete => Kind::Removed,
+                    },
+                    old: change.old_index().map(|it| it + 1),
+                    new: change.new_index().map(|it| it + 1),
+                    text: trim_newline(change.value()),
+                })
+                .collect();
+
+            Hunk {
+                header: format!(
+                    "@@ -{},{} +{},{} @@",
+                    old_range.start + 1,
+                    old_range.len(),
+                    new_range.start + 1,
+                    new_range.len()
+                ),
+                lines: render(&lines, &mut old_highlighter, &mut new_highlighter),
+            }
+        })
+        .collect();
+
+    FileDiff {
+        old_name: old_name.to_owned(),
+        new_name: new_name.to_owned(),
+        hunks,
+    }
+}
+
+/// Diffs `old` against `new` in the unified diff format.
+pub fn unified((old_name, old): (&str, &str), (new_name, new): (&str, &str)) -> String {
+    TextDiff::configure()
+        .timeout(TIMEOUT)
+        .diff_lines(old, new)
+        .unified_diff()
+        .context_radius(CONTEXT)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+/// Strips the `a/` and `b/` prefixes git uses, and anything following a tab
+/// (e.g. timestamps of `diff -u`).
+fn patch_path(path: &str) -> String {
+    let path = path.split('\t').next().unwrap_or(path).trim_end();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_owned()
+}
+
+/// Parses the start and length from one side of a hunk header, e.g. `-1,4`.
+fn hunk_range(range: &str) -> Option<(usize, usize)> {
+    let range = range.get(1..)?;
+    Some(match range.split_once(',') {
+        Some((start, len)) => (start.parse().ok()?, len.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    })
+}
+
+fn syntax_for<'a>(name: &str, syntaxes: &'a SyntaxSet) -> Option<&'a SyntaxReference> {
+    let ext = name.rsplit_once('.').map_or(name, |(_, ext)| ext);
+    syntaxes.find_syntax_by_token(ext)
+}
+
+/// Parses a patch in the unified diff format (e.g. from `git diff` or
+/// `git format-patch`), the code of every file is highlighted by its
+/// extension. Text outside of file diffs is ignored, returns `None` if
+/// `patch` contains no file diffs.
+pub fn parse(patch: &str, syntaxes: &SyntaxSet) -> Option<Vec<FileDiff>> {
+    let mut files = Vec::new();
+    let mut lines = patch.lines().peekable();
+    // Names from a `diff --git` line, used when there are no `---`/`+++`
+    // lines, e.g. for renames or binary files.
+    let mut git_names: Option<(String, String)> = None;
+
+    while let Some(line) = lines.next() {
+        if let Some(names) = line.strip_prefix("diff --git ") {
+            if let Some((old, new)) = git_names.take() {
+                files.push(FileDiff {
+                    old_name: old,
+                    new_name: new,
+                    hunks: Vec::new(),
+                });
+            }
+            git_names = names
+                .split_once(" b/")
+                .map(|(old, new)| (patch_path(old), new.to_owned()));
+            continue;
+        }
+        let Some(old_name) = line.strip_prefix("--- ") else {
+            continue;
+        };
+        let Some(new_name) = lines.next_if(|it| it.starts_with("+++ ")) else {
+            continue;
+        };
+        git_names = None;
+        let (old_name, new_name) = (patch_path(old_name), patch_path(&new_name[4..]));
+        let syntax = syntax_for(&new_name, syntaxes).or_else(|| syntax_for(&old_name, syntaxes));
+
+        let mut hunks = Vec::new();
+        while let Some(header) = lines.next_if(|it| it.starts_with("@@ ")) {
+            let mut ranges = header[3..].split(' ');
+            let (Some((mut old, mut old_len)), Some((mut new, mut new_len))) = (
+                ranges.next().and_then(hunk_range),
+                ranges.next().and_then(hunk_range),
+            ) else {
+                break;
+            };
+
+            let mut raw = Vec::new();
+            while old_len > 0 || new_len > 0 {
+                let Some(line) =
+                    lines.next_if(|it| it.is_empty() || it.starts_with([' ', '+', '-', '\\']))
+                else {
+                    break;
+                };
+                let (kind, text) = match line.split_at(line.len().min(1)) {
+                    ("+", text) => (Kind::Added, text),
+                    ("-", text) => (Kind::Removed, text),
+                    ("\\", _) => continue,
+                    // Some tools strip the space of empty context lines.
+                    (_, text) => (Kind::Context, text),
+                };
+                let (old_number, new_number) = (
+                    (kind != Kind::Added).then_some(old),
+                    (kind != Kind::Removed).then_some(new),
+                );
+                if kind != Kind::Added {
+                    old += 1;
+                    old_len = old_len.saturating_sub(1);
+                }
+                if kind != Kind::Removed {
+                    new += 1;
+                    new_len = new_len.saturating_sub(1);
+                }
+                raw.push(RawLine {
+                    kind,
+                    old: old_number,
+                    new: new_number,
+                    text,
+                });
+            }
+            // `\ No newline at end of file` following the last line.
+            lines.next_if(|it| it.starts_with('\\'));
+
+            // The state before the hunk is unknown, so every hunk starts fresh.
+            let mut old_highlighter = LineHighlighter::new(syntax, syntaxes);
+            let mut new_highlighter = LineHighlighter::new(syntax, syntaxes);
+            hunks.push(Hunk {
+                header: header.to_owned(),
+                lines: render(&raw, &mut old_highlighter, &mut new_highlighter),
+            });
+        }
+
+        files.push(FileDiff {
+            old_name,
+            new_name,
+            hunks,
+        });
+    }
+    if let Some((old, new)) = git_names {
+        files.push(FileDiff {
+            old_name: old,
+            new_name: new,
+            hunks: Vec::new(),
+        });
+    }
+
+    (!files.is_empty()).then_some(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(hunk: &Hunk) -> Vec<Kind> {
+        hunk.lines.iter().map(|line| line.kind).collect()
+    }
+
+    fn numbers(hunk: &Hunk) -> Vec<(Option<usize>, Option<usize>)> {
+        hunk.lines.iter().map(|line| (line.old, line.new)).collect()
+    }
+
+    fn diff_texts(old: &str, new: &str) -> FileDiff {
+        texts(("a", old), ("b", new), None, &SyntaxSet::new())
+    }
+
+    #[test]
+    fn texts_numbers_lines() {
+        let diff = diff_texts("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(diff.hunks.len(), 1);
+        let hunk = &diff.hunks[0];
+        assert_eq!(hunk.header, "@@ -1,3 +1,4 @@");
+        assert_eq!(
+            kinds(hunk),
+            [
+                Kind::Context,
+                Kind::Removed,
+                Kind::Added,
+                Kind::Context,
+                Kind::Added
+            ]
+        );
+        assert_eq!(
+            numbers(hunk),
+            [
+                (Some(1), Some(1)),
+                (Some(2), None),
+                (None, Some(2)),
+                (Some(3), Some(3)),
+                (None, Some(4))
+            ]
+        );
+        assert_eq!((diff.added(), diff.removed()), (2, 1));
+    }
+
+    #[test]
+    fn texts_of_equal_texts_have_no_hunks() {
+        assert!(diff_texts("a\nb\n", "a\nb\n").hunks.is_empty());
+    }
+
+    #[test]
+    fn texts_split_distant_changes_into_hunks() {
+        let old: String = (1..=20).map(|i| format!("{i}\n")).collect();
+        let new: String = (1..=20)
+            .map(|i| match i {
+                2 => "two\n".to_owned(),
+                19 => "nineteen\n".to_owned(),
+                i => format!("{i}\n"),
+            })
+            .collect();
+        let diff = diff_texts(&old, &new);
+        let headers: Vec<_> = diff.hunks.iter().map(|hunk| hunk.header.as_str()).collect();
+        assert_eq!(headers, ["@@ -1,5 +1,5 @@", "@@ -16,5 +16,5 @@"]);
+    }
+
+    #[test]
+    fn texts_without_trailing_newline() {
+        let diff = diff_texts("a\nb", "a\nb\n");
+        let hunk = &diff.hunks[0];
+        assert_eq!(kinds(hunk), [Kind::Context, Kind::Removed, Kind::Added]);
+        // The line ending is not part of the shown text.
+        assert_eq!(hunk.lines[1].html, "b");
+        assert_eq!(hunk.lines[2].html, "b");
+    }
+
+    #[test]
+    fn texts_mark_changed_words() {
+        let diff = diff_texts("let x = 1;\n", "let x = 2;\n");
+        let lines = &diff.hunks[0].lines;
+        assert_eq!(lines[0].html, r#"let x = <span class="mark">1;</span>"#);
+        assert_eq!(lines[1].html, r#"let x = <span class="mark">2;</span>"#);
+    }
+
+    #[test]
+    fn texts_do_not_mark_rewritten_lines() {
+        let diff = diff_texts("one two three\n", "four five six\n");
+        assert!(diff.hunks[0]
+            .lines
+            .iter()
+            .all(|line| !line.html.contains("mark")));
+    }
+
+    #[test]
+    fn texts_escape_html() {
+        let diff = diff_texts("<a>\n", "<b>\n");
+        assert_eq!(diff.hunks[0].lines[0].html, "&lt;a&gt;");
+    }
+
+    #[test]
+    fn rows_pair_removed_and_added_lines() {
+        let diff = diff_texts("a\nb\nc\n", "A\nB\nc\n");
+        let rows: Vec<_> = diff.hunks[0]
+            .rows()
+            .into_iter()
+            .map(|(old, new)| (old.and_then(|it| it.old), new.and_then(|it| it.new)))
+            .collect();
+        assert_eq!(
+            rows,
+            [(Some(1), Some(1)), (Some(2), Some(2)), (Some(3), Some(3))]
+        );
+    }
+
+    #[test]
+    fn unified_format() {
+        assert_eq!(
+            unified(("a", "a\nb\n"), ("b", "a\nc\n")),
+            "--- a\n+++ b\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n"
+        );
+    }
+
+    fn parse_patch(patch: &str) -> Vec<FileDiff> {
+        parse(patch, &SyntaxSet::new()).expect("contains file diffs")
+    }
+
+    fn names(files: &[FileDiff]) -> Vec<(&str, &str)> {
+        files
+            .iter()
+            .map(|file| (file.old_name.as_str(), file.new_name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn patch_path_strips_prefixes_and_timestamps() {
+        assert_eq!(patch_path("a/src/main.rs"), "src/main.rs");
+        assert_eq!(patch_path("b/src/main.rs"), "src/main.rs");
+        assert_eq!(
+            patch_path("old/file.txt\t2024-01-01 12:00:00.000000000 +0100"),
+            "old/file.txt"
+        );
+        assert_eq!(patch_path("/dev/null"), "/dev/null");
+    }
+
+    #[test]
+    fn hunk_range_with_and_without_length() {
+        assert_eq!(hunk_range("-12,5"), Some((12, 5)));
+        assert_eq!(hunk_range("+3"), Some((3, 1)));
+        assert_eq!(hunk_range("+0,0"), Some((0, 0)));
+        assert_eq!(hunk_range("-a,1"), None);
+        assert_eq!(hunk_range(""), None);
+    }
+
+    #[test]
+    fn parse_git_diff() {
+        let files = parse_patch(
+            "diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a042389 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@ mod tests
+ fn main() {
+-    println!(\"hello\");
++    println!(\"world\");
+ }
+
+@@ -10,2 +10,3 @@
+ a
++b
+ c
+",
+        );
+        assert_eq!(names(&files), [("src/lib.rs", "src/lib.rs")]);
+        let hunks = &files[0].hunks;
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].header, "@@ -1,4 +1,4 @@ mod tests");
+        assert_eq!(
+            kinds(&hunks[0]),
+            [
+                Kind::Context,
+                Kind::Removed,
+                Kind::Added,
+                Kind::Context,
+                Kind::Context
+            ]
+        );
+        // The empty context line without its leading space.
+        assert_eq!(numbers(&hunks[0])[4], (Some(4), Some(4)));
+        assert_eq!(
+            numbers(&hunks[1]),
+            [(Some(10), Some(10)), (None, Some(11)), (Some(11), Some(12))]
+        );
+        assert_eq!((files[0].added(), files[0].removed()), (2, 1));
+    }
+
+    #[test]
+    fn parse_no_newline_at_end_of_file() {
+        let files = parse_patch(
+            "--- a/x.txt
++++ b/x.txt
+@@ -1,2 +1,2 @@
+ a
+-b
+\\ No newline at end of file
++b
+\\ No newline at end of file
+--- a/y.txt
++++ b/y.txt
+@@ -1 +1 @@
+-c
++d
+",
+        );
+        assert_eq!(names(&files), [("x.txt", "x.txt"), ("y.txt", "y.txt")]);
+        assert_eq!(
+            kinds(&files[0].hunks[0]),
+            [Kind::Context, Kind::Removed, Kind::Added]
+        );
+        assert_eq!(
+            numbers(&files[1].hunks[0]),
+            [(Some(1), None), (None, Some(1))]
+        );
+    }
+
+    #[test]
+    fn parse_added_and_deleted_files() {
+        let files = parse_patch(
+            "diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..8baef1b
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++abc
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+index 8baef1b..0000000
+--- a/old.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-abc
+-def
+",
+        );
+        assert_eq!(
+            names(&files),
+            [("/dev/null", "new.txt"), ("old.txt", "/dev/null")]
+        );
+        assert_eq!((files[0].name(), files[1].name()), ("new.txt", "old.txt"));
+        assert_eq!(numbers(&files[0].hunks[0]), [(None, Some(1))]);
+        assert_eq!(
+            numbers(&files[1].hunks[0]),
+            [(Some(1), None), (Some(2), None)]
+        );
+    }
+
+    #[test]
+    fn parse_renames_and_binary_files() {
+        let files = parse_patch(
+            "diff --git a/old name.txt b/new name.txt
+similarity index 100%
+rename from old name.txt
+rename to new name.txt
+diff --git a/image.png b/image.png
+index 1f2e3d4..5a6b7c8 100644
+Binary files a/image.png and b/image.png differ
+diff --git a/c.txt b/c.txt
+--- a/c.txt
++++ b/c.txt
+@@ -1 +1 @@
+-a
++b
+",
+        );
+        assert_eq!(
+            names(&files),
+            [
+                ("old name.txt", "new name.txt"),
+                ("image.png", "image.png"),
+                ("c.txt", "c.txt")
+            ]
+        );
+        assert!(files[0].hunks.is_empty());
+        assert!(files[1].hunks.is_empty());
+        assert_eq!(files[2].hunks.len(), 1);
+    }
+
+    #[test]
+    fn parse_format_patch() {
+        let files = parse_patch(
+            "From 1234567890abcdef Mon Sep 17 00:00:00 2001
+From: Someone <someone@example.com>
+Subject: [PATCH] Change a line
+
+---
+ a.txt | 2 +-
+ 1 file changed, 1 insertion(+), 1 deletion(-)
+
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+--- a list item
++-- another item
+-- 
+2.43.0
+",
+        );
+        assert_eq!(names(&files), [("a.txt", "a.txt")]);
+        // Lines looking like file headers are part of the hunk.
+        assert_eq!(kinds(&files[0].hunks[0]), [Kind::Removed, Kind::Added]);
+        assert!(files[0].hunks[0].lines[0].html.starts_with("-- "));
+    }
+
+    #[test]
+    fn parse_diff_u_with_timestamps() {
+        let files = parse_patch(
+            "--- old/a.c\t2024-01-01 12:00:00.000000000 +0100
++++ new/a.c\t2024-01-02 12:00:00.000000000 +0100
+@@ -1 +1,2 @@
+ int x;
++int y;
+",
+        );
+        assert_eq!(names(&files), [("old/a.c", "new/a.c")]);
+        assert_eq!(
+            numbers(&files[0].hunks[0]),
+            [(Some(1), Some(1)), (None, Some(2))]
+        );
+    }
+
+    #[test]
+    fn parse_stops_at_invalid_hunk_headers() {
+        let files = parse_patch("--- a/x\n+++ b/x\n@@ -a +b @@\n-x\n+y\n");
+        assert!(files[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn parse_without_file_diffs() {
+        assert!(parse("just some text\n- a list\n", &SyntaxSet::new()).is_none());
+    }
+}
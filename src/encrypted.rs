@@ -0,0 +1,31 @@
+//! Format of end-to-end encrypted pastes. They are encrypted by the client
+//! with AES-256-GCM, the key never reaches the server.
+//!
+//! The stored paste is the JSON object
+//! `{"version": 1, "iv": <base64url>, "ciphertext": <base64url>}`, where `iv`
+//! is the 12 byte nonce and `ciphertext` includes the 16 byte tag.
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+
+const IV_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope {
+    version: u32,
+    iv: String,
+    ciphertext: String,
+}
+
+/// Checks that `data` is an envelope of an encrypted paste.
+pub fn is_valid(data: &[u8]) -> bool {
+    let Ok(envelope) = serde_json::from_slice::<Envelope>(data) else {
+        return false;
+    };
+    let decoded_len = |value: &str| BASE64_URL_SAFE_NO_PAD.decode(value).map(|it| it.len());
+    envelope.version == 1
+        && decoded_len(&envelope.iv) == Ok(IV_LENGTH)
+        && decoded_len(&envelope.ciphertext).is_ok_and(|len| len >= TAG_LENGTH)
+}
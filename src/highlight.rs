@@ -0,0 +1,140 @@
+use std::{fmt::Write, ops::Range};
+
+use askama::{Html, MarkupDisplay};
+use syntect::{
+    html::{ClassStyle, ClassedHTMLGenerator},
+    parsing::{
+        BasicScopeStackOp, ParseState, Scope, ScopeStack, ScopeStackOp, SyntaxReference, SyntaxSet,
+    },
+    util::LinesWithEndings,
+};
+
+/// Highlights `code` as HTML using classes prefixed with `code-`, returns
+/// `None` if the syntax fails to parse the code.
+pub fn html(code: &str, syntax: &SyntaxReference, syntaxes: &SyntaxSet) -> Option<String> {
+    let mut html_generator = ClassedHTMLGenerator::new_with_class_style(
+        syntax,
+        syntaxes,
+        ClassStyle::SpacedPrefixed { prefix: "code-" },
+    );
+
+    for line in LinesWithEndings::from(code) {
+        // Necessary for the used toml syntax
+        let line = &line.replace('\r', "");
+        html_generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+
+    Some(html_generator.finalize())
+}
+
+/// Highlights code line by line, producing balanced HTML for every line so
+/// lines can be arranged freely, e.g. in a diff.
+pub struct LineHighlighter<'a> {
+    syntaxes: &'a SyntaxSet,
+    /// `None` if there is no syntax or parsing failed, lines are only escaped.
+    state: Option<ParseState>,
+    stack: ScopeStack,
+}
+
+impl<'a> LineHighlighter<'a> {
+    pub fn new(syntax: Option<&SyntaxReference>, syntaxes: &'a SyntaxSet) -> Self {
+        Self {
+            syntaxes,
+            state: syntax.map(ParseState::new),
+            stack: ScopeStack::new(),
+        }
+    }
+
+    fn parse(&mut self, line: &str) -> Vec<(usize, ScopeStackOp)> {
+        let Some(state) = &mut self.state else {
+            return Vec::new();
+        };
+        match state.parse_line(&format!("{line}\n"), self.syntaxes) {
+            Ok(ops) => ops,
+            Err(_) => {
+                self.state = None;
+                Vec::new()
+            }
+        }
+    }
+
+    /// Advances the parse state by `line` without producing any output.
+    pub fn skip(&mut self, line: &str) {
+        for (_, op) in self.parse(line) {
+            // Errors only occur for invalid syntaxes, which stop highlighting anyway.
+            _ = self.stack.apply(&op);
+        }
+    }
+
+    /// Highlights `line` (without line ending), wrapping the byte ranges in
+    /// `marks` into `<span class="mark">`, `marks` need to be sorted and must
+    /// not overlap.
+    pub fn line(&mut self, line: &str, marks: &[Range<usize>]) -> String {
+        let line = line.trim_end_matches('\r');
+        let ops = self.parse(line);
+
+        let mut html = String::with_capacity(line.len() * 2);
+        for scope in self.stack.as_slice() {
+            open_span(&mut html, *scope);
+        }
+
+        let mut splits: Vec<usize> = ops
+            .iter()
+            .map(|(offset, _)| *offset)
+            .chain(marks.iter().flat_map(|mark| [mark.start, mark.end]))
+            .filter(|&offset| offset <= line.len())
+            .chain([line.len()])
+            .collect();
+        splits.sort_unstable();
+        splits.dedup();
+
+        let mut ops = ops.into_iter().peekable();
+        let mut start = 0;
+        for split in splits {
+            if let Some(text) = line.get(start..split).filter(|it| !it.is_empty()) {
+                if marks.iter().any(|mark| mark.contains(&start)) {
+                    write!(html, r#"<span class="mark">{}</span>"#, escape(text))
+                } else {
+                    write!(html, "{}", escape(text))
+                }
+                .expect("writing to string");
+                start = split;
+            }
+            while let Some((_, op)) = ops.next_if(|(offset, _)| *offset <= split) {
+                self.apply(&mut html, &op);
+            }
+        }
+        for (_, op) in ops {
+            self.apply(&mut html, &op);
+        }
+
+        html.push_str(&"</span>".repeat(self.stack.len()));
+        html
+    }
+
+    fn apply(&mut self, html: &mut String, op: &ScopeStackOp) {
+        _ = self.stack.apply_with_hook(op, |op, _| match op {
+            BasicScopeStackOp::Push(scope) => open_span(html, scope),
+            BasicScopeStackOp::Pop => html.push_str("</span>"),
+        });
+    }
+}
+
+fn escape(text: &str) -> MarkupDisplay<Html, &str> {
+    MarkupDisplay::new_unsafe(text, Html)
+}
+
+/// Opens a span with the same classes [`ClassedHTMLGenerator`] would use.
+fn open_span(html: &mut String, scope: Scope) {
+    html.push_str(r#"<span class=""#);
+    for (i, atom) in scope.build_string().split('.').enumerate() {
+        if i > 0 {
+            html.push(' ');
+        }
+        html.push_str("code-");
+        html.push_str(atom);
+    }
+    html.push_str(r#"">"#);
+}
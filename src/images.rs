@@ -0,0 +1,263 @@
+//! Thumbnail generation and removal of privacy sensitive metadata (EXIF, GPS,
+//! text chunks) from uploaded images.
+
+use std::io::Cursor;
+
+use image::{
+    codecs::webp::WebPEncoder, DynamicImage, ImageDecoder, ImageReader, ImageResult, Limits,
+};
+
+/// Thumbnails fit into a square of this size.
+pub const THUMBNAIL_SIZE: u32 = 800;
+
+/// Creates a WebP thumbnail of `data`, returns `None` if the image is already
+/// small enough to be used directly.
+pub fn thumbnail(data: &[u8]) -> ImageResult<Option<Vec<u8>>> {
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(16_384);
+    limits.max_image_height = Some(16_384);
+    limits.max_alloc = Some(256 * 1024 * 1024);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let (width, height) = decoder.dimensions();
+    if width <= THUMBNAIL_SIZE && height <= THUMBNAIL_SIZE {
+        return Ok(None);
+    }
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).into_rgba8();
+    let mut out = Vec::new();
+    thumbnail.write_with_encoder(WebPEncoder::new_lossless(&mut out))?;
+    Ok(Some(out))
+}
+
+/// Removes metadata from JPEG and PNG files, other data is returned as `None`.
+///
+/// This operates on the container format and never re-encodes the image.
+pub fn strip_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        strip_jpeg(data)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        strip_png(data)
+    } else {
+        None
+    }
+}
+
+const APP0: u8 = 0xE0;
+const APP1: u8 = 0xE1;
+const APP2: u8 = 0xE2;
+const APP15: u8 = 0xEF;
+const SOS: u8 = 0xDA;
+const COM: u8 = 0xFE;
+
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut rest = &data[2..];
+    let mut orientation = None;
+    loop {
+        let [0xFF, marker, ..] = *rest else {
+            return None;
+        };
+        // Markers without a length.
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) || marker == 0xFF {
+            out.extend_from_slice(&rest[..2]);
+            rest = &rest[2..];
+            continue;
+        }
+        // Everything following start of scan is image data.
+        if marker == SOS {
+            out.extend_from_slice(rest);
+            break;
+        }
+        // The length includes its own two bytes.
+        let length = u16::from_be_bytes([*rest.get(2)?, *rest.get(3)?]) as usize;
+        if length < 2 {
+            return None;
+        }
+        let segment = rest.get(..2 + length)?;
+        match marker {
+            APP1 if segment[4..].starts_with(b"Exif\0\0") => {
+                orientation = exif_orientation(&segment[10..]);
+            }
+            // JFIF and ICC color profiles are necessary for correct display.
+            APP0 | APP2 => out.extend_from_slice(segment),
+            APP1..=APP15 | COM => {}
+            _ => {
+                // Keep the orientation, as images would appear rotated otherwise.
+                if let Some(orientation) = orientation.take() {
+                    out.extend_from_slice(&orientation_segment(orientation));
+                }
+                out.extend_from_slice(segment);
+            }
+        }
+        rest = &rest[2 + length..];
+    }
+    Some(out)
+}
+
+/// Reads the orientation tag from the first IFD of a TIFF structure.
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let bytes: [u8; 4] = tiff.get(4..8)?.try_into().ok()?;
+    let ifd = if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    } as usize;
+    let entries = u16_at(ifd)?;
+    (0..entries as usize)
+        .map(|index| ifd + 2 + index * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|&orientation| orientation != 1)
+}
+
+/// An APP1 segment containing only the orientation tag.
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut segment = vec![0xFF, APP1, 0, 34];
+    segment.extend_from_slice(b"Exif\0\0");
+    // TIFF header, big endian with the first IFD following directly.
+    segment.extend_from_slice(b"MM\0*\0\0\0\x08");
+    // One entry: orientation, SHORT, count 1
+    segment.extend_from_slice(&[0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1]);
+    segment.extend_from_slice(&orientation.to_be_bytes());
+    // Padding of the value and no next IFD.
+    segment.extend_from_slice(&[0; 6]);
+    segment
+}
+
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    const REMOVED: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..8]);
+    let mut rest = &data[8..];
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        // length, type, data, crc
+        let chunk = rest.get(..length.checked_add(12)?)?;
+        if !REMOVED.iter().any(|it| &chunk[4..8] == *it) {
+            out.extend_from_slice(chunk);
+        }
+        rest = &rest[chunk.len()..];
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOI: [u8; 2] = [0xFF, 0xD8];
+
+    fn jpeg(segments: &[&[u8]]) -> Vec<u8> {
+        let mut data = SOI.to_vec();
+        for segment in segments {
+            data.extend_from_slice(segment);
+        }
+        data
+    }
+
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        // The CRC is not checked.
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    const PNG: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+    #[test]
+    fn jpeg_removes_comments_and_exif() {
+        let quant = [0xFF, 0xDB, 0, 4, 1, 2];
+        let scan = [0xFF, SOS, 0, 2, 0xAB, 0xCD];
+        let data = jpeg(&[
+            &[0xFF, APP0, 0, 4, b'J', b'F'],
+            &[0xFF, COM, 0, 5, b'a', b'b', b'c'],
+            &[0xFF, APP1, 0, 8, b'E', b'x', b'i', b'f', 0, 0],
+            &quant,
+            &scan,
+        ]);
+        assert_eq!(
+            strip_metadata(&data).unwrap(),
+            jpeg(&[&[0xFF, APP0, 0, 4, b'J', b'F'], &quant, &scan])
+        );
+    }
+
+    #[test]
+    fn jpeg_keeps_orientation() {
+        let mut exif = vec![0xFF, APP1, 0, 34];
+        exif.extend_from_slice(&orientation_segment(6)[4..]);
+        let quant = [0xFF, 0xDB, 0, 2];
+        let scan = [0xFF, SOS, 0, 2];
+        let stripped = strip_metadata(&jpeg(&[&exif, &quant, &scan])).unwrap();
+        assert_eq!(stripped, jpeg(&[&orientation_segment(6), &quant, &scan]));
+        assert_eq!(exif_orientation(&stripped[12..]), Some(6));
+    }
+
+    #[test]
+    fn jpeg_rejects_short_segment_lengths() {
+        for length in [0, 1] {
+            let data = jpeg(&[&[0xFF, APP1, 0, length], &[0xFF, SOS, 0, 2]]);
+            assert_eq!(strip_metadata(&data), None);
+        }
+    }
+
+    #[test]
+    fn jpeg_rejects_truncated_data() {
+        assert_eq!(strip_metadata(&jpeg(&[&[0xFF, APP1, 0, 8, b'E']])), None);
+        assert_eq!(strip_metadata(&jpeg(&[&[0xFF, APP1, 0]])), None);
+        assert_eq!(strip_metadata(&jpeg(&[&[0xFF]])), None);
+        // No start of scan.
+        assert_eq!(strip_metadata(&jpeg(&[&[0xFF, 0xDB, 0, 2]])), None);
+    }
+
+    #[test]
+    fn png_removes_text_chunks() {
+        let header = png_chunk(b"IHDR", &[0; 13]);
+        let end = png_chunk(b"IEND", &[]);
+        let mut data = PNG.to_vec();
+        for chunk in [&header, &png_chunk(b"tEXt", b"a\0b"), &end] {
+            data.extend_from_slice(chunk);
+        }
+        let mut stripped = PNG.to_vec();
+        stripped.extend_from_slice(&header);
+        stripped.extend_from_slice(&end);
+        assert_eq!(strip_metadata(&data).unwrap(), stripped);
+    }
+
+    #[test]
+    fn png_rejects_truncated_chunks() {
+        let mut data = PNG.to_vec();
+        data.extend_from_slice(&png_chunk(b"IHDR", &[0; 13])[..10]);
+        assert_eq!(strip_metadata(&data), None);
+
+        let mut data = PNG.to_vec();
+        data.extend_from_slice(&[0, 0]);
+        assert_eq!(strip_metadata(&data), None);
+
+        let mut data = PNG.to_vec();
+        data.extend_from_slice(&u32::MAX.to_be_bytes());
+        data.extend_from_slice(b"tEXt");
+        assert_eq!(strip_metadata(&data), None);
+    }
+}
@@ -1,24 +1,44 @@
 use std::{env, path::PathBuf};
 
 use actix_web::{web::Data, App, HttpServer};
-use anyhow::Result;
+use actix_web_lab::middleware::from_fn;
+use anyhow::{Context, Result};
 use bonsaidb::local::config::StorageConfiguration;
 use include_dir::include_dir;
 use log::{error, info};
 use syntect::{
     dumps::from_uncompressed_data,
+    highlighting::ThemeSet,
     parsing::{SyntaxDefinition, SyntaxSet},
 };
 
 use config::Config;
 use db::DB;
 
+mod ansi;
+mod archive;
+mod blocklist;
 mod config;
+mod csp;
+mod data;
 mod db;
+mod diff;
+mod encrypted;
+mod highlight;
+mod images;
+mod markdown;
+mod owner;
+mod password;
 mod simple;
+mod terminal;
+mod usercontent;
 mod util;
+mod vault;
 
-pub const RESERVED_URLS: &[&str] = &["raw", "download", "delete"];
+pub const RESERVED_URLS: &[&str] = &[
+    "raw", "download", "delete", "thumb", "edit", "fork", "diff", "unlock", "account", "mine",
+    "api", "expire", "restore", "report", "admin",
+];
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -37,8 +57,27 @@ async fn main() -> Result<()> {
     }
 
     let config = Config::load(&config_path)?;
+    let secret = config.encryption_secret()?;
 
-    let database = Data::new(DB::new().await?);
+    if env::args().nth(1).as_deref() == Some("encrypt-database") {
+        let secret = secret
+            .context("Set `encryption_key` or `encryption_key_file` to encrypt the database")?;
+        DB::encrypt(&secret).await?;
+        println!("The database is now encrypted");
+        return Ok(());
+    }
+    if env::args().nth(1).as_deref() == Some("blocklist") {
+        let database = DB::new(secret.as_deref()).await?;
+        return blocklist::command(&env::args().skip(2).collect::<Vec<_>>(), &database).await;
+    }
+
+    let theme = ThemeSet::load_defaults()
+        .themes
+        .remove(&config.terminal_theme)
+        .with_context(|| format!("Unknown terminal theme `{}`", config.terminal_theme))?;
+
+    let database = Data::new(DB::new(secret.as_deref()).await?);
+    let cookie_keys = Data::new(owner::CookieKeys::load(&config)?);
     let config = Data::new(config);
     let syntaxes: SyntaxSet = from_uncompressed_data(include_bytes!("../grammars/syntaxes.bin"))
         .expect("included syntaxes are valid");
@@ -60,12 +99,19 @@ async fn main() -> Result<()> {
         );
     }
     let syntaxes = Data::new(syntaxes.build());
+    let theme = Data::new(theme);
+    let attempts = Data::new(password::Attempts::default());
 
     HttpServer::new(move || {
         App::new()
             .app_data(database.clone())
             .app_data(config.clone())
             .app_data(syntaxes.clone())
+            .app_data(theme.clone())
+            .app_data(attempts.clone())
+            .app_data(cookie_keys.clone())
+            .wrap(from_fn(usercontent::restrict))
+            .wrap(from_fn(csp::headers))
             .service(simple::scope())
     })
     .bind("0.0.0.0:8000")?
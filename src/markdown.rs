@@ -0,0 +1,130 @@
+//! Rendering of Markdown pastes (CommonMark with GitHub extensions) to
+//! sanitized HTML.
+
+use std::collections::HashMap;
+
+use ammonia::Builder;
+use pulldown_cmark::{html::push_html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use syntect::parsing::SyntaxSet;
+
+use crate::highlight;
+
+/// Prefix added to all `id`s in the rendered document, so they can not clash
+/// with the ones used by the page itself.
+const ID_PREFIX: &str = "md-";
+
+fn slug(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            slug.push(c);
+        } else if c.is_whitespace() {
+            slug.push('-');
+        }
+    }
+    slug
+}
+
+/// Renders `markdown` to HTML, fenced code blocks are highlighted using
+/// `syntaxes` and headings get anchors.
+pub fn render(markdown: &str, syntaxes: &SyntaxSet) -> String {
+    let parser = Parser::new_ext(
+        markdown,
+        Options::ENABLE_GFM
+            | Options::ENABLE_TABLES
+            | Options::ENABLE_TASKLISTS
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_FOOTNOTES,
+    );
+
+    let mut events = Vec::new();
+    let mut slugs = HashMap::<String, usize>::new();
+    // Start of the current heading or code block in `events`.
+    let mut start = None;
+    for event in parser {
+        match event {
+            Event::Start(Tag::Heading { .. } | Tag::CodeBlock(_)) => {
+                start = Some(events.len());
+                events.push(event);
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let start = start.take().expect("heading was started");
+                let text: String = events[start + 1..]
+                    .iter()
+                    .filter_map(|event| match event {
+                        Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                let mut id = slug(&text);
+                let count = slugs.entry(id.clone()).or_default();
+                if *count > 0 {
+                    id = format!("{id}-{count}");
+                }
+                *count += 1;
+                events.push(Event::InlineHtml(
+                    format!(r##" <a class="anchor" href="#{ID_PREFIX}{id}">#</a>"##).into(),
+                ));
+                events[start] = Event::Start(Tag::Heading {
+                    level,
+                    id: Some(id.into()),
+                    classes: Vec::new(),
+                    attrs: Vec::new(),
+                });
+                events.push(Event::End(TagEnd::Heading(level)));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let start = start.take().expect("code block was started");
+                let Event::Start(Tag::CodeBlock(kind)) = &events[start] else {
+                    unreachable!("code block starts with `Start(CodeBlock)`")
+                };
+                let token = match kind {
+                    CodeBlockKind::Fenced(info) => info.split([' ', ',', '{']).next(),
+                    CodeBlockKind::Indented => None,
+                }
+                .filter(|it| !it.is_empty())
+                .map(str::to_owned);
+                let code: String = events
+                    .drain(start + 1..)
+                    .filter_map(|event| match event {
+                        Event::Text(text) => Some(text),
+                        _ => None,
+                    })
+                    .collect::<Vec<CowStr>>()
+                    .concat();
+                if let Some(html) = token
+                    .and_then(|token| syntaxes.find_syntax_by_token(&token))
+                    .and_then(|syntax| highlight::html(&code, syntax, syntaxes))
+                {
+                    events[start] = Event::Html(format!("<pre><code>{html}</code></pre>").into());
+                } else {
+                    events.push(Event::Text(code.into()));
+                    events.push(Event::End(TagEnd::CodeBlock));
+                }
+            }
+            event => events.push(event),
+        }
+    }
+
+    let mut html = String::with_capacity(markdown.len() * 3 / 2);
+    push_html(&mut html, events.into_iter());
+
+    let mut sanitizer = Builder::default();
+    sanitizer
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("span", ["class"])
+        .add_tag_attributes("a", ["class"])
+        .id_prefix(Some(ID_PREFIX))
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            // Only allow the checkboxes generated for task lists.
+            ("input", "type") if value != "checkbox" => None,
+            ("span", "class") if !value.split(' ').all(|it| it.starts_with("code-")) => None,
+            ("a", "class") if value != "anchor" => None,
+            _ => Some(value.into()),
+        });
+    for heading in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+        sanitizer.add_tag_attributes(heading, ["id"]);
+    }
+    sanitizer.clean(&html).to_string()
+}
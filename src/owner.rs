@@ -0,0 +1,165 @@
+//! Identity of the uploader of a paste, kept in a private cookie that is
+//! encrypted and authenticated with a key from the [`Config`].
+
+use std::{fs, io, path::Path};
+
+use actix_web::cookie::{time::Duration, Cookie, CookieBuilder, CookieJar, Key, SameSite};
+use anyhow::{bail, Context, Result};
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::Sha256;
+
+use crate::config::Config;
+
+const OWNER_COOKIE: &str = "OWNER";
+/// Lifetime of the owner cookie, it is renewed with every upload.
+const LIFETIME: Duration = Duration::days(365);
+/// Minimum length of a secret to derive a key from.
+const MIN_SECRET_LENGTH: usize = 32;
+/// Length of owner ids, which were stored as plain cookie values before.
+const ID_LENGTH: usize = 20;
+
+pub struct CookieKeys {
+    /// Key used for new cookies.
+    current: Key,
+    /// Previous keys, cookies encrypted with them are still accepted.
+    old: Vec<Key>,
+    /// Accept cookies containing the plain id, see [`is_plain_id`].
+    plain: bool,
+    secure: bool,
+}
+
+impl CookieKeys {
+    /// Derives the keys from `cookie_key` and `old_cookie_keys`, generating
+    /// and storing a secret in `cookie_key_file` when no key is configured.
+    pub fn load(config: &Config) -> Result<Self> {
+        let current = match &config.cookie_key {
+            Some(secret) => secret.clone(),
+            None => generated_secret(&config.cookie_key_file)?,
+        };
+        Ok(Self {
+            current: derive(&current)?,
+            old: config
+                .old_cookie_keys
+                .iter()
+                .map(|secret| derive(secret))
+                .collect::<Result<_>>()?,
+            plain: config.plain_owner_cookies,
+            secure: config.secure_cookies,
+        })
+    }
+
+    /// Whether cookies are restricted to HTTPS.
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Owner id from the cookie, `None` if there is no valid cookie.
+    pub fn owner(&self, cookies: &CookieJar) -> Option<String> {
+        let cookie = cookies.get(OWNER_COOKIE)?;
+        [&self.current]
+            .into_iter()
+            .chain(&self.old)
+            .find_map(|key| cookies.private(key).decrypt(cookie.clone()))
+            .map(|cookie| cookie.value().to_owned())
+            .or_else(|| {
+                (self.plain && is_plain_id(cookie.value())).then(|| cookie.value().to_owned())
+            })
+    }
+
+    /// Owner id from the cookie, or a new one. The cookie is always set again
+    /// to renew it and to encrypt it with the current key.
+    pub fn owner_or_create(&self, cookies: &mut CookieJar) -> String {
+        let owner = self
+            .owner(cookies)
+            .unwrap_or_else(|| Alphanumeric.sample_string(&mut rand::thread_rng(), ID_LENGTH));
+        self.set_owner(cookies, owner.clone());
+        owner
+    }
+
+    /// Sets the cookie to `owner`, e.g. to log in as a user.
+    pub fn set_owner(&self, cookies: &mut CookieJar, owner: String) {
+        cookies
+            .private_mut(&self.current)
+            .add(self.cookie(owner).max_age(LIFETIME).finish());
+    }
+
+    /// Removes the cookie, the next upload gets a new anonymous owner.
+    pub fn remove_owner(&self, cookies: &mut CookieJar) {
+        cookies.remove(self.cookie(String::new()).finish());
+    }
+
+    fn cookie(&self, owner: String) -> CookieBuilder<'static> {
+        Cookie::build(OWNER_COOKIE, owner)
+            .path("/")
+            .secure(self.secure)
+            .http_only(true)
+            .same_site(SameSite::Lax)
+    }
+
+    /// Token for the link deleting `id`, tied to the owner so other sites can
+    /// not make an owner delete their paste.
+    pub fn delete_token(&self, cookies: &CookieJar, id: &str) -> String {
+        let owner = self.owner(cookies).unwrap_or_default();
+        BASE64_URL_SAFE_NO_PAD.encode(self.delete_mac(&owner, id).finalize().into_bytes())
+    }
+
+    pub fn verify_delete_token(&self, cookies: &CookieJar, id: &str, token: &str) -> bool {
+        self.verify_owner_token(&self.owner(cookies).unwrap_or_default(), id, token)
+    }
+
+    /// Whether `token` is the delete token `owner` got for `id`, which proves
+    /// ownership without the cookie, e.g. for editing.
+    pub fn verify_owner_token(&self, owner: &str, id: &str, token: &str) -> bool {
+        BASE64_URL_SAFE_NO_PAD
+            .decode(token)
+            .is_ok_and(|token| self.delete_mac(owner, id).verify_slice(&token).is_ok())
+    }
+
+    /// Keyed hash of an IP address, which can not be reversed by trying all
+    /// addresses without the key.
+    pub fn hash_ip(&self, ip: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.current.signing())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"ip\0");
+        mac.update(ip.as_bytes());
+        BASE64_URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+
+    fn delete_mac(&self, owner: &str, id: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.current.signing())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"delete\0");
+        mac.update(owner.as_bytes());
+        mac.update(b"\0");
+        mac.update(id.as_bytes());
+        mac
+    }
+}
+
+fn derive(secret: &str) -> Result<Key> {
+    if secret.len() < MIN_SECRET_LENGTH {
+        bail!("Cookie keys need to be at least {MIN_SECRET_LENGTH} bytes long");
+    }
+    Ok(Key::derive_from(secret.as_bytes()))
+}
+
+fn generated_secret(path: &Path) -> Result<String> {
+    let context = || format!("Accessing cookie key file `{}`", path.display());
+    match fs::read_to_string(path) {
+        Ok(secret) => Ok(secret.trim_end().to_owned()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let secret = Alphanumeric.sample_string(&mut rand::thread_rng(), 64);
+            fs::write(path, &secret).with_context(context)?;
+            Ok(secret)
+        }
+        Err(e) => Err(e).with_context(context),
+    }
+}
+
+/// Cookies set before they were encrypted contained the plain id, they are
+/// accepted with `plain_owner_cookies` and replaced on the next upload.
+fn is_plain_id(value: &str) -> bool {
+    value.len() == ID_LENGTH && value.bytes().all(|b| b.is_ascii_alphanumeric())
+}
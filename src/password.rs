@@ -0,0 +1,82 @@
+//! Password protection of pastes, the passwords are stored as Argon2 hashes
+//! and failed attempts are limited per paste.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{self, rand_core::OsRng, PasswordHash, SaltString},
+    Argon2, PasswordHasher, PasswordVerifier,
+};
+
+/// Failed attempts allowed per paste within [`WINDOW`].
+const MAX_FAILURES: u32 = 5;
+const WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Stored instead of a hash while an upload is in progress, no password
+/// matches it.
+pub const LOCKED: &str = "locked";
+
+pub fn hash(password: &str) -> password_hash::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+pub fn verify(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// Hash of a random password, checked for unknown accounts so they take as
+/// long to reject as wrong passwords.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash(SaltString::generate(&mut OsRng).as_str()).expect("hashing a random password")
+});
+
+/// Like [`verify`], but `None`, e.g. for an unknown account, never matches.
+pub fn verify_existing(password: &str, hash: Option<&str>) -> bool {
+    let verified = verify(password, hash.unwrap_or(&DUMMY_HASH));
+    verified && hash.is_some()
+}
+
+/// Token remembering that the password was entered, derived from the salted
+/// hash so it is invalidated with the password.
+pub fn unlock_token(hash: &str) -> Option<String> {
+    Some(PasswordHash::new(hash).ok()?.hash?.to_string())
+}
+
+/// Failed attempts per paste, kept in memory.
+#[derive(Default)]
+pub struct Attempts(Mutex<HashMap<String, (u32, Instant)>>);
+
+impl Attempts {
+    /// Time until `name` can be tried again, if there were too many failed
+    /// attempts.
+    pub fn blocked(&self, name: &str) -> Option<Duration> {
+        let failures = self.0.lock().expect("not poisoned");
+        let &(count, since) = failures.get(name)?;
+        let elapsed = since.elapsed();
+        (count >= MAX_FAILURES && elapsed < WINDOW).then(|| WINDOW - elapsed)
+    }
+
+    pub fn failed(&self, name: &str) {
+        let mut failures = self.0.lock().expect("not poisoned");
+        failures.retain(|_, (_, since)| since.elapsed() < WINDOW);
+        failures
+            .entry(name.to_owned())
+            .or_insert((0, Instant::now()))
+            .0 += 1;
+    }
+
+    pub fn succeeded(&self, name: &str) {
+        self.0.lock().expect("not poisoned").remove(name);
+    }
+}
@@ -2,37 +2,64 @@ use std::{borrow::Borrow, fmt::Display, str};
 
 use actix_multipart::Multipart;
 use actix_web::{
-    cookie::{Cookie, CookieJar},
+    cookie::{Cookie, CookieJar, SameSite},
+    delete,
     dev::HttpServiceFactory,
     error::ErrorInternalServerError,
     get,
     guard::GuardContext,
     http::{
         header::{self, ContentDisposition, DispositionParam, DispositionType},
-        StatusCode,
+        Method, StatusCode,
     },
     post, routes,
-    web::{Bytes, Data, Payload},
-    HttpResponse, Responder, ResponseError, Result,
+    web::{self, Bytes, Data, Payload, Query},
+    CustomizeResponder, HttpRequest, HttpResponse, HttpResponseBuilder, Responder, ResponseError,
+    Result,
 };
 use actix_web_lab::extract::Path;
 use askama::Template;
 use askama_actix::TemplateToResponse;
+use base64::{prelude::BASE64_STANDARD, Engine};
 use chrono::{Duration, Utc};
-use futures::{future::ready, StreamExt, TryStreamExt};
+use futures::{future::ready, stream, StreamExt, TryStreamExt};
 use futures_util::Stream;
-use mime_guess::mime::{self, APPLICATION_OCTET_STREAM, IMAGE, VIDEO};
-use rand::distributions::{Alphanumeric, DistString};
+use mime_guess::mime::{self, APPLICATION_OCTET_STREAM, APPLICATION_PDF, AUDIO, IMAGE, VIDEO};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde::Deserialize;
-use syntect::{html::ClassedHTMLGenerator, parsing::SyntaxSet, util::LinesWithEndings};
+use sha2::{Digest, Sha256};
+use syntect::{highlighting::Theme, parsing::SyntaxSet};
 
 use crate::{
+    ansi,
+    archive::{self, ArchiveError},
+    blocklist,
     config::Config,
-    db::{DateTime, DB},
+    data::{self, ParseError, View},
+    db::{self, DateTime, File, Metadata, DB},
+    diff::{self, FileDiff},
+    encrypted, highlight, images, markdown,
+    owner::CookieKeys,
+    password::{self, Attempts},
+    terminal::{self, Colors},
     util::{AddCookieJar, Cookies},
 };
 
-const OWNER_COOKIE: &str = "OWNER";
+/// Prefix of the cookies remembering the pastes a password was entered for,
+/// followed by the id.
+const UNLOCK_COOKIE: &str = "UNLOCK_";
+/// Query parameter passing the unlock token to the raw host.
+const UNLOCK_QUERY: &str = "unlock";
+/// Header to send the password of a paste, both when viewing and uploading.
+const PASSWORD_HEADER: &str = "X-Paste-Password";
+/// Characters escaped in file names used as a URL path segment.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'~');
+/// Files larger than this are not highlighted.
+const MAX_DISPLAY_SIZE: u64 = 50_000;
 
 #[derive(Template)]
 #[template(path = "404.html")]
@@ -40,12 +67,32 @@ struct NotFound;
 
 pub fn scope() -> impl HttpServiceFactory {
     (
-        delete_entry,
-        raw,
-        download,
+        (
+            account,
+            login,
+            register,
+            logout,
+            create_api_key,
+            revoke_api_key,
+        ),
+        (
+            mine,
+            post_mine,
+            api_pastes,
+            api_bulk,
+            admin_reports,
+            moderate,
+            get_blocklist,
+            import_blocklist,
+            unblock,
+        ),
+        (delete_entry, expire, restore, report_paste),
+        (raw_entry, download_group_file),
+        (edit, post_edit, put_raw, raw_revision, fork, unlock),
+        (raw_diff, diff_view),
+        (thumbnail, raw, download),
         get_ext,
-        post_form,
-        post_raw,
+        (post_form, post_json, post_raw),
         index,
         redir_down,
     )
@@ -58,32 +105,72 @@ async fn redir_down() -> impl Responder {
         .finish()
 }
 
+#[derive(Template)]
+#[template(path = "upload.html")]
+struct Upload {
+    /// Text the form is prefilled with.
+    code: String,
+    extension: String,
+    /// Paste a new revision is submitted for, instead of creating a new one.
+    edit: Option<String>,
+    /// Delete token the edit form was opened with, see [`editable`].
+    token: Option<String>,
+    /// File name of the paste that is forked.
+    parent: Option<String>,
+}
+
 #[get("/")]
 async fn index() -> impl Responder {
-    #[derive(Template)]
-    #[template(path = "upload.html")]
-    struct Upload;
-
-    Upload
+    Upload {
+        code: String::new(),
+        extension: String::new(),
+        edit: None,
+        token: None,
+        parent: None,
+    }
 }
 
 #[routes]
-#[get("download/{id}.{ext}")]
+#[get("download/{id:[^/.]+}.{ext}")]
 #[get("download/{id}")]
-async fn download(Path(file_name): Path<FileName>, db: Data<DB>) -> Result<impl Responder> {
+async fn download(
+    Path(file_name): Path<FileName>,
+    request: HttpRequest,
+    db: Data<DB>,
+    attempts: Data<Attempts>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
     if let Some(file) = db
         .load_file(&file_name.id)
         .await
         .map_err(ErrorInternalServerError)?
     {
+        if let Some(redirect) =
+            raw_redirect(&file_name.id, file.metadata(), &request, &config, &attempts).await?
+        {
+            return Ok(redirect.customize());
+        }
+        check_password(&file_name.id, file.metadata(), &request, &attempts).await??;
+        if !file.metadata().files.is_empty() {
+            let files = group_contents(&db, &file_name.id, &file.metadata().files).await?;
+            let zip = web::block(move || archive::zip(files))
+                .await
+                .map_err(ErrorInternalServerError)?
+                .map_err(ErrorInternalServerError)?;
+            return Ok(HttpResponse::Ok()
+                .content_type("application/zip")
+                .insert_header(ContentDisposition {
+                    disposition: DispositionType::Attachment,
+                    parameters: vec![DispositionParam::Filename(format!("{}.zip", file_name.id))],
+                })
+                .body(zip)
+                .customize());
+        }
         Ok(HttpResponse::Ok()
-            .content_type(
-                file_name
-                    .ext
-                    .as_deref()
-                    .map(|ext| mime_guess::from_ext(ext).first_or_octet_stream())
-                    .unwrap_or(APPLICATION_OCTET_STREAM),
-            )
+            .content_type(paste_content_type(
+                file.metadata(),
+                file_name.ext.as_deref(),
+            ))
             .insert_header(ContentDisposition {
                 disposition: DispositionType::Attachment,
                 parameters: vec![DispositionParam::Filename(file_name.to_string())],
@@ -103,32 +190,182 @@ async fn download(Path(file_name): Path<FileName>, db: Data<DB>) -> Result<impl
     }
 }
 
+/// Loads the contents of all files of the multi-file paste `name`.
+async fn group_contents(
+    database: &DB,
+    name: &str,
+    files: &[String],
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut contents = Vec::with_capacity(files.len());
+    for file_name in files {
+        if let Some(file) = database
+            .load_group_file(name, file_name)
+            .await
+            .map_err(ErrorInternalServerError)?
+        {
+            contents.push((
+                file_name.clone(),
+                file.contents()
+                    .await
+                    .map_err(ErrorInternalServerError)?
+                    .into_vec()
+                    .await
+                    .map_err(ErrorInternalServerError)?,
+            ));
+        }
+    }
+    Ok(contents)
+}
+
+#[derive(Deserialize)]
+struct GroupPath {
+    id: String,
+    name: String,
+}
+
+#[get("download/{id:[^/.]+}/{name}")]
+async fn download_group_file(
+    Path(path): Path<GroupPath>,
+    request: HttpRequest,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    group_file(path, &request, &database, &attempts, &config, true).await
+}
+
+async fn group_file(
+    GroupPath { id, name }: GroupPath,
+    request: &HttpRequest,
+    database: &DB,
+    attempts: &Attempts,
+    config: &Config,
+    attachment: bool,
+) -> Result<CustomizeResponder<HttpResponse>> {
+    let content_type = content_type(name.rsplit_once('.').map(|(_, ext)| ext));
+    let Some(paste) = database
+        .load_file(&id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    if let Some(redirect) = raw_redirect(&id, paste.metadata(), request, config, attempts).await? {
+        return Ok(redirect.customize());
+    }
+    check_password(&id, paste.metadata(), request, attempts).await??;
+    let Some(file) = database
+        .load_group_file(&id, &name)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+
+    let mut response = raw_response(content_type);
+    if attachment {
+        response.insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(name)],
+        });
+    }
+    Ok(response
+        .streaming(
+            file.contents()
+                .await
+                .map_err(ErrorInternalServerError)?
+                .map_ok(From::from),
+        )
+        .customize())
+}
+
+#[derive(Deserialize)]
+struct RawQuery {
+    color: Option<String>,
+}
+
 #[routes]
-#[get("raw/{id}.{ext}")]
+#[get("raw/{id:[^/.]+}.{ext}")]
 #[get("raw/{id}")]
-#[get("{id}.{ext}", guard = "no_browser")]
+#[get("{id:[^/.]+}.{ext}", guard = "no_browser")]
 #[get("{id}", guard = "no_browser")]
+#[allow(clippy::too_many_arguments)]
 async fn raw(
     Path(FileName { id, ext }): Path<FileName>,
+    Query(query): Query<RawQuery>,
+    request: HttpRequest,
     database: Data<DB>,
+    syntaxes: Data<SyntaxSet>,
+    theme: Data<Theme>,
+    config: Data<Config>,
+    attempts: Data<Attempts>,
 ) -> Result<impl Responder> {
     if let Some(file) = database
         .load_file(&id)
         .await
         .map_err(ErrorInternalServerError)?
     {
-        Ok(HttpResponse::Ok()
-            .content_type(
-                ext.as_deref()
-                    .map(|ext| mime_guess::from_ext(ext).first_or_octet_stream())
-                    .unwrap_or(APPLICATION_OCTET_STREAM),
-            )
-            .streaming(
-                file.contents()
-                    .await
-                    .map_err(ErrorInternalServerError)?
-                    .map_ok(From::from),
-            )
+        let content_type = paste_content_type(file.metadata(), ext.as_deref());
+        // Non browser clients requesting `{id}` get the file on any host.
+        if request.path().starts_with("/raw/") {
+            if let Some(redirect) =
+                raw_redirect(&id, file.metadata(), &request, &config, &attempts).await?
+            {
+                return Ok(redirect.customize());
+            }
+        }
+        check_password(&id, file.metadata(), &request, &attempts).await??;
+        // Multi-file pastes are listed with the URLs of their files.
+        if !file.metadata().files.is_empty() {
+            let base_url = base_url(&config);
+            return Ok(HttpResponse::Ok()
+                .content_type(mime::TEXT_PLAIN_UTF_8)
+                .body(
+                    file.metadata()
+                        .files
+                        .iter()
+                        .map(|name| {
+                            format!(
+                                "{base_url}raw/{id}/{}\n",
+                                utf8_percent_encode(name, PATH_SEGMENT)
+                            )
+                        })
+                        .collect::<String>(),
+                )
+                .customize());
+        }
+        let colors = colors(query.color.as_deref(), &request);
+        let syntax = ext
+            .as_ref()
+            .filter(|_| !file.metadata().encrypted)
+            .and_then(|ext| syntaxes.find_syntax_by_token(ext));
+        let contents = file.contents().await.map_err(ErrorInternalServerError)?;
+
+        if let (Some(colors), Some(syntax)) = (colors, syntax) {
+            if contents.len() < MAX_DISPLAY_SIZE {
+                if let Ok(code) =
+                    String::from_utf8(contents.to_vec().await.map_err(ErrorInternalServerError)?)
+                {
+                    if let Ok(highlighted) =
+                        terminal::highlight(&code, syntax, &syntaxes, &theme, colors)
+                    {
+                        return Ok(HttpResponse::Ok()
+                            .content_type("text/x-ansi; charset=utf-8")
+                            .body(highlighted)
+                            .customize());
+                    }
+                }
+            }
+        }
+
+        Ok(raw_response(content_type)
+            .streaming(contents.map_ok(From::from))
             .customize())
     } else {
         Ok(NotFound
@@ -138,6 +375,163 @@ async fn raw(
     }
 }
 
+#[routes]
+#[get("thumb/{id:[^/.]+}.{ext}")]
+#[get("thumb/{id}")]
+async fn thumbnail(
+    Path(file_name): Path<FileName>,
+    request: HttpRequest,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+) -> Result<impl Responder> {
+    let Some(file) = database
+        .load_file(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .filter(|file| !file.metadata().encrypted)
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    check_password(&file_name.id, file.metadata(), &request, &attempts).await??;
+
+    let thumbnail = if let Some(thumbnail) = database
+        .load_thumbnail(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        Some(
+            thumbnail
+                .contents()
+                .await
+                .map_err(ErrorInternalServerError)?
+                .into_vec()
+                .await
+                .map_err(ErrorInternalServerError)?,
+        )
+    } else {
+        let data = file
+            .contents()
+            .await
+            .map_err(ErrorInternalServerError)?
+            .into_vec()
+            .await
+            .map_err(ErrorInternalServerError)?;
+        // Formats that can not be decoded (e.g. SVG) are shown as is.
+        let thumbnail = web::block(move || images::thumbnail(&data).ok().flatten())
+            .await
+            .map_err(ErrorInternalServerError)?;
+        if let Some(thumbnail) = &thumbnail {
+            database
+                .store_thumbnail(&file_name.id, thumbnail)
+                .await
+                .map_err(ErrorInternalServerError)?;
+        }
+        thumbnail
+    };
+
+    Ok(if let Some(thumbnail) = thumbnail {
+        HttpResponse::Ok()
+            .content_type("image/webp")
+            .body(thumbnail)
+            .customize()
+    } else {
+        HttpResponse::Found()
+            .append_header((header::LOCATION, format!("../raw/{file_name}")))
+            .finish()
+            .customize()
+    })
+}
+
+/// Colors requested for terminal output, either via the `color` query
+/// parameter or the `Accept` header.
+fn colors(query: Option<&str>, request: &HttpRequest) -> Option<Colors> {
+    query.and_then(Colors::from_query).or_else(|| {
+        request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|it| it.to_str().ok())
+            .and_then(Colors::from_accept)
+    })
+}
+
+/// Response serving a file as is.
+fn raw_response(content_type: mime::Mime) -> HttpResponseBuilder {
+    let mut response = HttpResponse::Ok();
+    if is_active(&content_type) {
+        // Documents like HTML and SVG can contain scripts, which must not run
+        // on our origin when the file is opened directly.
+        response.insert_header((
+            header::CONTENT_SECURITY_POLICY,
+            "sandbox; default-src 'none'; img-src data:; style-src 'unsafe-inline'",
+        ));
+    }
+    response.content_type(content_type);
+    response
+}
+
+/// Redirects requests for files on the main host to `raw_url`, so they never
+/// run on the origin of the main site. The unlock cookie of password protected
+/// pastes is not sent to the raw host, so the password is checked here and the
+/// redirect passes the unlock token on.
+async fn raw_redirect(
+    id: &str,
+    metadata: &Metadata,
+    request: &HttpRequest,
+    config: &Config,
+    attempts: &Attempts,
+) -> Result<Option<HttpResponse>> {
+    let Some(raw_url) = &config.raw_url else {
+        return Ok(None);
+    };
+    if config.is_raw_host(request.connection_info().host()) {
+        return Ok(None);
+    }
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or(request.path(), |it| it.as_str());
+    let mut location = raw_url
+        .join(path.trim_start_matches('/'))
+        .map_err(ErrorInternalServerError)?;
+    if let Some(hash) = &metadata.password {
+        check_password(id, metadata, request, attempts).await??;
+        if let Some(token) = password::unlock_token(hash) {
+            location.query_pairs_mut().append_pair(UNLOCK_QUERY, &token);
+        }
+    }
+    Ok(Some(
+        HttpResponse::TemporaryRedirect()
+            .append_header((header::LOCATION, location.as_str()))
+            .finish(),
+    ))
+}
+
+/// Content types browsers open as documents that can run scripts.
+fn is_active(content_type: &mime::Mime) -> bool {
+    matches!(
+        content_type.essence_str(),
+        "text/html" | "application/xhtml+xml" | "text/xml" | "application/xml" | "image/svg+xml"
+    )
+}
+
+fn content_type(ext: Option<&str>) -> mime::Mime {
+    ext.map(|ext| mime_guess::from_ext(ext).first_or_octet_stream())
+        .unwrap_or(APPLICATION_OCTET_STREAM)
+}
+
+/// Content type of a paste, encrypted pastes are served as their JSON
+/// envelope regardless of the extension.
+fn paste_content_type(metadata: &Metadata, ext: Option<&str>) -> mime::Mime {
+    if metadata.encrypted {
+        mime::APPLICATION_JSON
+    } else {
+        content_type(ext)
+    }
+}
+
 fn no_browser(it: &GuardContext) -> bool {
     const MOZILLA: &[u8] = b"Mozilla";
     !it.head()
@@ -155,13 +549,19 @@ fn no_browser(it: &GuardContext) -> bool {
 }
 
 #[routes]
-#[get("{id}.{ext}")]
+#[get("{id:[^/.]+}.{ext}")]
 #[get("{id}")]
+#[allow(clippy::too_many_arguments)]
 async fn get_ext(
     Path(file_name): Path<FileName>,
+    Query(ViewQuery { path, rev, view }): Query<ViewQuery>,
+    request: HttpRequest,
     database: Data<DB>,
     syntaxes: Data<SyntaxSet>,
+    attempts: Data<Attempts>,
     Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
 ) -> Result<impl Responder> {
     Ok(
         if let Some(file) = database
@@ -169,59 +569,227 @@ async fn get_ext(
             .await
             .map_err(ErrorInternalServerError)?
         {
+            if let Err(error) =
+                check_password(&file_name.id, file.metadata(), &request, &attempts).await?
+            {
+                return Ok(Locked::response(file_name, error));
+            }
             let metadata = file.metadata();
-            let delete_at = metadata.delete_at;
-            let owner = cookies
-                .iter()
-                .any(|cookie| cookie.name_value() == (OWNER_COOKIE, &metadata.owner));
-            let mime = file_name
+            let viewer = keys.owner(&cookies);
+            let owner = viewer.as_ref() == Some(&metadata.owner);
+            let times = db::revision_times(&file);
+            let mut page = Page {
+                delete_at: metadata.delete_at,
+                owner,
+                privileged: owner && viewer.is_some_and(|viewer| config.is_admin(&viewer)),
+                deletion_request: metadata.deletion_request.clone(),
+                delete_token: keys.delete_token(&cookies, &file_name.id),
+                // The unlock cookie is only sent to our own host, which
+                // redirects to the raw host with the unlock token.
+                raw_url: match &config.raw_url {
+                    Some(raw_url) if metadata.password.is_none() => raw_url.to_string(),
+                    _ => String::new(),
+                },
+                copy: None,
+                history: History {
+                    selected: rev.unwrap_or(times.len()),
+                    times,
+                    parent: metadata.parent.clone(),
+                },
+                file_name,
+            };
+
+            if metadata.encrypted {
+                #[derive(Template)]
+                #[template(path = "encrypted.html")]
+                struct Encrypted {
+                    page: Page,
+                }
+
+                return Ok(Encrypted { page }.to_response().customize());
+            }
+
+            if !metadata.files.is_empty() {
+                #[derive(Template)]
+                #[template(path = "group.html")]
+                struct Group {
+                    files: Vec<GroupFile>,
+                    page: Page,
+                }
+
+                let files = group_contents(&database, &page.file_name.id, &metadata.files)
+                    .await?
+                    .into_iter()
+                    .map(|(name, contents)| {
+                        let token = name.rsplit_once('.').map_or(name.as_str(), |(_, ext)| ext);
+                        let content = if mime_guess::from_ext(token)
+                            .first()
+                            .is_some_and(|mime| mime.type_() == IMAGE)
+                        {
+                            GroupContent::Image
+                        } else if contents.len() as u64 >= MAX_DISPLAY_SIZE {
+                            GroupContent::TooLarge
+                        } else if let Ok(code) = String::from_utf8(contents) {
+                            syntaxes
+                                .find_syntax_by_token(token)
+                                .and_then(|syntax| highlight::html(&code, syntax, &syntaxes))
+                                .map_or(GroupContent::Text(code), GroupContent::Highlighted)
+                        } else {
+                            GroupContent::Binary
+                        };
+                        GroupFile { name, content }
+                    })
+                    .collect();
+
+                // Extensions do not apply to the whole group.
+                page.file_name.ext = None;
+                return Ok(Group { files, page }.to_response().customize());
+            }
+
+            let mime = page
+                .file_name
                 .ext
                 .as_ref()
                 .and_then(|ext| mime_guess::from_ext(ext).first());
-            let syntax = file_name
+            let syntax = page
+                .file_name
                 .ext
                 .as_ref()
                 .and_then(|ext| syntaxes.find_syntax_by_token(ext));
+            let file = if page.history.selected == page.history.times.len() {
+                file
+            } else if let Some(file) = database
+                .load_revision(&page.file_name.id, page.history.selected)
+                .await
+                .map_err(ErrorInternalServerError)?
+            {
+                file
+            } else {
+                return Ok(NotFound
+                    .to_response()
+                    .customize()
+                    .with_status(StatusCode::NOT_FOUND));
+            };
             let file = file.contents().await.map_err(ErrorInternalServerError)?;
 
+            if let Some(kind) = page
+                .file_name
+                .ext
+                .as_deref()
+                .and_then(archive::Kind::from_ext)
+            {
+                let data = file.to_vec().await.map_err(ErrorInternalServerError)?;
+                return Ok(if let Some(path) = path {
+                    let entry = web::block({
+                        let path = path.clone();
+                        move || archive::read(kind, &data, &path, MAX_DISPLAY_SIZE)
+                    })
+                    .await
+                    .map_err(ErrorInternalServerError)?;
+                    let syntax = path
+                        .rsplit_once('.')
+                        .and_then(|(_, ext)| syntaxes.find_syntax_by_token(ext));
+
+                    #[derive(Template)]
+                    #[template(path = "archive_entry.html")]
+                    struct ArchiveEntry {
+                        path: String,
+                        code: Option<String>,
+                        highlighted: Option<String>,
+                        error: Option<String>,
+                        page: Page,
+                    }
+
+                    let (code, error) = match entry {
+                        Ok(Some(entry)) => match String::from_utf8(entry) {
+                            Ok(code) => (Some(code), None),
+                            Err(_) => (None, Some("This entry is not a text file.".to_owned())),
+                        },
+                        Ok(None) => (None, Some("This entry does not exist.".to_owned())),
+                        Err(ArchiveError::TooLarge) => (
+                            None,
+                            Some("This entry is too large to be displayed.".to_owned()),
+                        ),
+                        Err(err) => (None, Some(err.to_string())),
+                    };
+
+                    ArchiveEntry {
+                        highlighted: code
+                            .as_deref()
+                            .zip(syntax)
+                            .and_then(|(code, syntax)| highlight::html(code, syntax, &syntaxes)),
+                        page: page.with_copy(code.clone()),
+                        code,
+                        error,
+                        path,
+                    }
+                    .to_response()
+                    .customize()
+                } else {
+                    #[derive(Template)]
+                    #[template(path = "archive.html")]
+                    struct Archive {
+                        entries: Vec<archive::Entry>,
+                        error: Option<ArchiveError>,
+                        page: Page,
+                    }
+
+                    let (entries, error) = match web::block(move || archive::list(kind, &data))
+                        .await
+                        .map_err(ErrorInternalServerError)?
+                    {
+                        Ok(entries) => (entries, None),
+                        Err(err) => (Vec::new(), Some(err)),
+                    };
+
+                    Archive {
+                        entries,
+                        error,
+                        page,
+                    }
+                    .to_response()
+                    .customize()
+                });
+            }
+
             match mime {
                 Some(mime) if mime.type_() == IMAGE => {
                     #[derive(Template)]
                     #[template(path = "image.html")]
                     struct Image {
-                        file_name: FileName,
-                        delete_at: Option<DateTime>,
-                        owner: bool,
-                        copy: Option<String>,
+                        page: Page,
                     }
 
-                    Image {
-                        file_name,
-                        delete_at,
-                        owner,
-                        copy: None,
-                    }
-                    .to_response()
+                    Image { page }.to_response()
                 }
                 Some(mime) if mime.type_() == VIDEO => {
                     #[derive(Template)]
                     #[template(path = "video.html")]
                     struct Video {
-                        file_name: FileName,
-                        delete_at: Option<DateTime>,
-                        owner: bool,
-                        copy: Option<String>,
+                        page: Page,
                     }
 
-                    Video {
-                        file_name,
-                        delete_at,
-                        owner,
-                        copy: None,
+                    Video { page }.to_response()
+                }
+                Some(mime) if mime.type_() == AUDIO => {
+                    #[derive(Template)]
+                    #[template(path = "audio.html")]
+                    struct Audio {
+                        page: Page,
                     }
-                    .to_response()
+
+                    Audio { page }.to_response()
+                }
+                Some(mime) if mime == APPLICATION_PDF => {
+                    #[derive(Template)]
+                    #[template(path = "pdf.html")]
+                    struct Pdf {
+                        page: Page,
+                    }
+
+                    Pdf { page }.to_response()
                 }
-                _ if file.len() < 50_000 => {
+                _ if file.len() < MAX_DISPLAY_SIZE => {
                     if let Ok(file) =
                         String::from_utf8(file.to_vec().await.map_err(ErrorInternalServerError)?)
                     {
@@ -229,63 +797,139 @@ async fn get_ext(
                         #[template(path = "code.html")]
                         struct UnHighlighted {
                             code: String,
-                            file_name: FileName,
-                            delete_at: Option<DateTime>,
-                            owner: bool,
-                            copy: Option<String>,
+                            page: Page,
+                        }
+
+                        #[derive(Template)]
+                        #[template(path = "code.html", escape = "none")]
+                        struct Highlighted {
+                            code: String,
+                            page: Page,
                         }
 
-                        if let Some(syntax) = syntax {
-                            let mut html_generator = ClassedHTMLGenerator::new_with_class_style(
-                                syntax,
-                                syntaxes.as_ref(),
-                                syntect::html::ClassStyle::SpacedPrefixed { prefix: "code-" },
-                            );
-
-                            for line in LinesWithEndings::from(&file) {
-                                // Necessary for the used toml syntax
-                                let line = &line.replace('\r', "");
-                                if html_generator
-                                    .parse_html_for_line_which_includes_newline(line)
-                                    .is_err()
-                                {
-                                    return Ok(UnHighlighted {
-                                        code: file.clone(),
-                                        file_name,
-                                        delete_at,
-                                        owner,
-                                        copy: Some(file),
-                                    }
-                                    .to_response()
-                                    .customize());
-                                }
+                        let is_ansi = matches!(page.file_name.ext.as_deref(), Some("ansi" | "log"))
+                            || syntax.is_none() && ansi::detect(&file);
+                        let data = match page.file_name.ext.as_deref() {
+                            Some("json") => Some(("JSON", data::json(&file).map(View::Json))),
+                            Some("csv") => Some(("CSV", data::table(&file, None).map(View::Table))),
+                            Some("tsv") => {
+                                Some(("TSV", data::table(&file, Some(b'\t')).map(View::Table)))
+                            }
+                            Some("toml") => {
+                                Some(("TOML", data::validate_toml(&file).map(|()| View::Valid)))
+                            }
+                            Some("yaml" | "yml") => {
+                                Some(("YAML", data::validate_yaml(&file).map(|()| View::Valid)))
+                            }
+                            _ => None,
+                        };
+
+                        if is_ansi {
+                            Highlighted {
+                                code: ansi::to_html(&file),
+                                page: page.with_copy(Some(file)),
+                            }
+                            .to_response()
+                        } else if let Some((format, Err(error))) = data {
+                            #[derive(Template)]
+                            #[template(path = "invalid.html")]
+                            struct Invalid {
+                                format: &'static str,
+                                error: ParseError,
+                                source: Option<String>,
+                                page: Page,
+                            }
+
+                            Invalid {
+                                format,
+                                error,
+                                source: syntax
+                                    .and_then(|syntax| highlight::html(&file, syntax, &syntaxes)),
+                                page: page.with_copy(Some(file)),
+                            }
+                            .to_response()
+                        } else if let Some((_, Ok(View::Json(rendered)))) = data {
+                            #[derive(Template)]
+                            #[template(path = "json.html")]
+                            struct Json {
+                                rendered: String,
+                                source: Option<String>,
+                                page: Page,
+                            }
+
+                            Json {
+                                rendered,
+                                source: syntax
+                                    .and_then(|syntax| highlight::html(&file, syntax, &syntaxes)),
+                                page: page.with_copy(Some(file)),
+                            }
+                            .to_response()
+                        } else if let Some((_, Ok(View::Table(table)))) = data {
+                            #[derive(Template)]
+                            #[template(path = "table.html")]
+                            struct DataTable {
+                                table: data::Table,
+                                source: Option<String>,
+                                page: Page,
+                            }
+
+                            DataTable {
+                                table,
+                                source: syntax
+                                    .and_then(|syntax| highlight::html(&file, syntax, &syntaxes)),
+                                page: page.with_copy(Some(file)),
+                            }
+                            .to_response()
+                        } else if let Some(diffs) =
+                            matches!(page.file_name.ext.as_deref(), Some("diff" | "patch"))
+                                .then(|| diff::parse(&file, &syntaxes))
+                                .flatten()
+                        {
+                            #[derive(Template)]
+                            #[template(path = "patch.html")]
+                            struct Patch {
+                                diffs: Vec<FileDiff>,
+                                split: bool,
+                                source: Option<String>,
+                                page: Page,
                             }
 
+                            Patch {
+                                diffs,
+                                split: view.as_deref() == Some("split"),
+                                source: syntax
+                                    .and_then(|syntax| highlight::html(&file, syntax, &syntaxes)),
+                                page: page.with_copy(Some(file)),
+                            }
+                            .to_response()
+                        } else if matches!(page.file_name.ext.as_deref(), Some("md" | "markdown")) {
                             #[derive(Template)]
-                            #[template(path = "code.html", escape = "none")]
-                            struct Highlighted {
-                                code: String,
-                                file_name: FileName,
-                                delete_at: Option<DateTime>,
-                                owner: bool,
-                                copy: Option<String>,
+                            #[template(path = "markdown.html")]
+                            struct Markdown {
+                                rendered: String,
+                                source: Option<String>,
+                                page: Page,
                             }
 
+                            Markdown {
+                                rendered: markdown::render(&file, &syntaxes),
+                                source: syntax
+                                    .and_then(|syntax| highlight::html(&file, syntax, &syntaxes)),
+                                page: page.with_copy(Some(file)),
+                            }
+                            .to_response()
+                        } else if let Some(code) =
+                            syntax.and_then(|syntax| highlight::html(&file, syntax, &syntaxes))
+                        {
                             Highlighted {
-                                code: html_generator.finalize(),
-                                file_name,
-                                delete_at,
-                                owner,
-                                copy: Some(file),
+                                code,
+                                page: page.with_copy(Some(file)),
                             }
                             .to_response()
                         } else {
                             UnHighlighted {
                                 code: file.clone(),
-                                file_name,
-                                delete_at,
-                                owner,
-                                copy: Some(file),
+                                page: page.with_copy(Some(file)),
                             }
                             .to_response()
                         }
@@ -293,38 +937,20 @@ async fn get_ext(
                         #[derive(Template)]
                         #[template(path = "wrong_type.html")]
                         struct WrongType {
-                            file_name: FileName,
-                            delete_at: Option<DateTime>,
-                            owner: bool,
-                            copy: Option<String>,
+                            page: Page,
                         }
 
-                        WrongType {
-                            file_name,
-                            delete_at,
-                            owner,
-                            copy: None,
-                        }
-                        .to_response()
+                        WrongType { page }.to_response()
                     }
                 }
                 _ => {
                     #[derive(Template)]
                     #[template(path = "too_large.html")]
                     struct TooLarge {
-                        file_name: FileName,
-                        delete_at: Option<DateTime>,
-                        owner: bool,
-                        copy: Option<String>,
+                        page: Page,
                     }
 
-                    TooLarge {
-                        file_name,
-                        delete_at,
-                        owner,
-                        copy: None,
-                    }
-                    .to_response()
+                    TooLarge { page }.to_response()
                 }
             }
             .customize()
@@ -337,58 +963,1876 @@ async fn get_ext(
     )
 }
 
+enum GroupContent {
+    Highlighted(String),
+    Text(String),
+    Image,
+    Binary,
+    TooLarge,
+}
+
+struct GroupFile {
+    name: String,
+    content: GroupContent,
+}
+
 #[derive(Deserialize)]
-struct FileName {
-    id: String,
-    ext: Option<String>,
+struct ViewQuery {
+    /// Entry of an archive.
+    path: Option<String>,
+    /// Revision to show instead of the current one.
+    rev: Option<usize>,
+    /// `split` for a side-by-side view of patches.
+    view: Option<String>,
 }
 
-impl Display for FileName {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let Self { id, ext } = self;
-        write!(f, "{id}")?;
-        if let Some(ext) = ext {
-            write!(f, ".{ext}")?;
-        }
-        Ok(())
+/// What every view of a paste shows besides its contents, see `get.html`.
+struct Page {
+    file_name: FileName,
+    delete_at: Option<DateTime>,
+    /// Whether the viewer owns the paste.
+    owner: bool,
+    /// Allowed to make the paste permanent.
+    privileged: bool,
+    deletion_request: Option<db::DeletionRequest>,
+    delete_token: String,
+    /// Prefix of links to the raw contents, empty for the own host.
+    raw_url: String,
+    /// Text for the copy button.
+    copy: Option<String>,
+    history: History,
+}
+
+impl Page {
+    fn with_copy(self, copy: Option<String>) -> Self {
+        Self { copy, ..self }
     }
 }
 
+/// Revisions of a paste and the one it was forked from.
+struct History {
+    /// Creation times of all revisions, the last one being the current.
+    times: Vec<DateTime>,
+    /// 1-based
+    selected: usize,
+    /// File name of the paste this one was forked from.
+    parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ArchivePath {
+    id: String,
+    /// Archive format, the extension the paste was uploaded with if missing.
+    ext: Option<String>,
+    path: String,
+}
+
+/// Downloads a member of an archive, `raw/{id}/{name}` of multi-file pastes
+/// are their files.
 #[routes]
-#[delete("{id}.{ext}")]
-#[delete("{id}")]
-#[get("delete/{id}.{ext}")]
-#[get("delete/{id}")]
-async fn delete_entry(
-    Path(FileName { id, .. }): Path<FileName>,
-    config: Data<Config>,
+#[get("raw/{id:[^/.]+}.{ext}/{path:.*}")]
+#[get("raw/{id:[^/.]+}/{path:.*}")]
+async fn raw_entry(
+    Path(ArchivePath { id, ext, path }): Path<ArchivePath>,
+    request: HttpRequest,
     database: Data<DB>,
-    Cookies(cookies): Cookies,
-) -> Result<impl Responder> {
-    let owner = database
-        .file_owner(&id)
+    attempts: Data<Attempts>,
+    config: Data<Config>,
+) -> Result<CustomizeResponder<HttpResponse>> {
+    let Some(file) = database
+        .load_file(&id)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .filter(|file| !file.metadata().encrypted)
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    if let Some(redirect) = raw_redirect(&id, file.metadata(), &request, &config, &attempts).await?
+    {
+        return Ok(redirect.customize());
+    }
+    if ext.is_none() && !file.metadata().files.is_empty() {
+        let path = GroupPath { id, name: path };
+        return group_file(path, &request, &database, &attempts, &config, false).await;
+    }
+    let Some(kind) = ext
+        .as_deref()
+        .or(file.metadata().extension.as_deref())
+        .and_then(archive::Kind::from_ext)
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    check_password(&id, file.metadata(), &request, &attempts).await??;
+    let data = file
+        .contents()
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_vec()
         .await
         .map_err(ErrorInternalServerError)?;
 
-    if matches!(owner, Some(owner) if
-        cookies
-            .iter()
-            .any(|cookie| cookie.name_value() == (OWNER_COOKIE, &owner))
-    ) {
+    let entry = web::block({
+        let path = path.clone();
+        move || archive::read(kind, &data, &path, u64::MAX)
+    })
+    .await
+    .map_err(ErrorInternalServerError)??;
+
+    Ok(if let Some(entry) = entry {
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        raw_response(content_type(name.rsplit_once('.').map(|(_, ext)| ext)))
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(name.to_owned())],
+            })
+            .body(entry)
+            .customize()
+    } else {
+        NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND)
+    })
+}
+
+#[derive(Deserialize)]
+struct FileName {
+    id: String,
+    ext: Option<String>,
+}
+
+impl Display for FileName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { id, ext } = self;
+        write!(f, "{id}")?;
+        if let Some(ext) = ext {
+            write!(f, ".{ext}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteQuery {
+    token: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DeleteError {
+    #[error("The link to delete this paste is invalid, reload the paste and try again")]
+    InvalidToken,
+}
+
+impl ResponseError for DeleteError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
+#[routes]
+#[delete("{id:[^/.]+}.{ext}")]
+#[delete("{id}")]
+#[get("delete/{id:[^/.]+}.{ext}")]
+#[get("delete/{id}")]
+async fn delete_entry(
+    Path(FileName { id, .. }): Path<FileName>,
+    Query(DeleteQuery { token }): Query<DeleteQuery>,
+    request: HttpRequest,
+    config: Data<Config>,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    let bearer = api_key_owner(&request, &database).await?;
+    // Links can be followed or embedded by other sites, the `DELETE` method
+    // can not be sent cross-origin without the consent of CORS. Other sites
+    // don't know the API key either.
+    if bearer.is_none()
+        && request.method() == Method::GET
+        && !token.is_some_and(|token| keys.verify_delete_token(&cookies, &id, &token))
+    {
+        return Err(DeleteError::InvalidToken.into());
+    }
+
+    let owner = database
+        .file_owner(&id)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let requester = bearer.or_else(|| keys.owner(&cookies));
+
+    if owner.is_some() && owner == requester {
         database
             .delete_at(&id, Utc::now())
             .await
             .map_err(ErrorInternalServerError)?
     } else {
         database
-            .delete_at(&id, Utc::now() + config.time_to_delete)
+            .request_deletion(&id, requester, Utc::now() + config.time_to_delete)
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/"))
+        .finish())
+}
+
+#[derive(Debug, thiserror::Error)]
+enum PasswordError {
+    #[error("This paste is protected by a password")]
+    Required,
+    #[error("The password is incorrect")]
+    Wrong,
+    #[error("Too many incorrect passwords, try again in {} minutes", .0.as_secs().div_ceil(60))]
+    TooManyAttempts(std::time::Duration),
+}
+
+impl ResponseError for PasswordError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PasswordError::Required | PasswordError::Wrong => StatusCode::UNAUTHORIZED,
+            PasswordError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let PasswordError::TooManyAttempts(wait) = self {
+            response.insert_header((header::RETRY_AFTER, wait.as_secs().to_string()));
+        } else {
+            response.insert_header((
+                header::WWW_AUTHENTICATE,
+                r#"Basic realm="paste", charset="UTF-8""#,
+            ));
+        }
+        response
+            .content_type(mime::TEXT_PLAIN_UTF_8)
+            .body(self.to_string())
+    }
+}
+
+/// Password sent with `request`, either in [`PASSWORD_HEADER`] or via basic
+/// authentication, the user name is ignored.
+fn request_password(request: &HttpRequest) -> Option<String> {
+    let headers = request.headers();
+    if let Some(password) = headers.get(PASSWORD_HEADER) {
+        return password.to_str().ok().map(str::to_owned);
+    }
+    let credentials = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let credentials = BASE64_STANDARD.decode(credentials.trim()).ok()?;
+    let (_, password) = str::from_utf8(&credentials).ok()?.split_once(':')?;
+    Some(password.to_owned())
+}
+
+/// Checks `password` against `hash`, counting failures against the paste `id`.
+async fn verify_password(
+    id: &str,
+    password: String,
+    hash: String,
+    attempts: &Attempts,
+) -> Result<Result<(), PasswordError>> {
+    if let Some(wait) = attempts.blocked(id) {
+        return Ok(Err(PasswordError::TooManyAttempts(wait)));
+    }
+    if web::block(move || password::verify(password.trim(), &hash))
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        attempts.succeeded(id);
+        Ok(Ok(()))
+    } else {
+        attempts.failed(id);
+        Ok(Err(PasswordError::Wrong))
+    }
+}
+
+/// Checks that `request` may access the paste `id`, i.e. it has no password,
+/// the password was entered before, is sent with the request or the request
+/// carries the unlock token from a [`raw_redirect`].
+async fn check_password(
+    id: &str,
+    metadata: &Metadata,
+    request: &HttpRequest,
+    attempts: &Attempts,
+) -> Result<Result<(), PasswordError>> {
+    let Some(hash) = metadata.password.clone() else {
+        return Ok(Ok(()));
+    };
+    let unlock_token = password::unlock_token(&hash);
+    let unlocked = request
+        .cookie(&format!("{UNLOCK_COOKIE}{id}"))
+        .is_some_and(|cookie| Some(cookie.value()) == unlock_token.as_deref())
+        || url::form_urlencoded::parse(request.query_string().as_bytes()).any(|(key, value)| {
+            key == UNLOCK_QUERY && Some(value.as_ref()) == unlock_token.as_deref()
+        });
+    if unlocked {
+        return Ok(Ok(()));
+    }
+    let Some(password) = request_password(request) else {
+        return Ok(Err(PasswordError::Required));
+    };
+    verify_password(id, password, hash, attempts).await
+}
+
+/// [`check_password`] for files belonging to the paste `id`, like revisions.
+async fn check_paste_password(
+    id: &str,
+    request: &HttpRequest,
+    database: &DB,
+    attempts: &Attempts,
+) -> Result<Result<(), PasswordError>> {
+    match database
+        .load_file(id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        Some(file) => check_password(id, file.metadata(), request, attempts).await,
+        None => Ok(Ok(())),
+    }
+}
+
+/// Cookie remembering that the password for `id` was entered.
+fn unlock_cookie(id: &str, hash: &str, secure: bool) -> Option<Cookie<'static>> {
+    Some(
+        Cookie::build(
+            format!("{UNLOCK_COOKIE}{id}"),
+            password::unlock_token(hash)?,
+        )
+        .path("/")
+        .secure(secure)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .finish(),
+    )
+}
+
+#[derive(Template)]
+#[template(path = "password.html")]
+struct Locked {
+    file_name: FileName,
+    error: Option<PasswordError>,
+}
+
+impl Locked {
+    fn response(file_name: FileName, error: PasswordError) -> CustomizeResponder<HttpResponse> {
+        let status = error.status_code();
+        Locked {
+            file_name,
+            // Not an error before a password was entered.
+            error: (!matches!(error, PasswordError::Required)).then_some(error),
+        }
+        .to_response()
+        .customize()
+        .with_status(status)
+    }
+}
+
+#[derive(Deserialize)]
+struct UnlockForm {
+    password: String,
+}
+
+/// Password prompt submitted by browsers.
+#[routes]
+#[post("unlock/{id:[^/.]+}.{ext}")]
+#[post("unlock/{id}")]
+async fn unlock(
+    Path(file_name): Path<FileName>,
+    web::Form(UnlockForm { password }): web::Form<UnlockForm>,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    let Some(file) = database
+        .load_file(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    let mut response = HttpResponse::SeeOther();
+    response.append_header((header::LOCATION, format!("../{file_name}")));
+    if let Some(hash) = file.metadata().password.clone() {
+        if let Err(error) =
+            verify_password(&file_name.id, password, hash.clone(), &attempts).await?
+        {
+            return Ok(Locked::response(file_name, error));
+        }
+        if let Some(cookie) = unlock_cookie(&file_name.id, &hash, keys.secure()) {
+            response.cookie(cookie);
+        }
+    }
+    Ok(response.finish().customize())
+}
+
+/// Prefix of the keys failed logins are counted under in [`Attempts`],
+/// followed by the user name.
+const LOGIN_ATTEMPTS: &str = "account:";
+const MAX_NAME_LENGTH: usize = 32;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+enum AccountError {
+    #[error("User names consist of up to {MAX_NAME_LENGTH} letters, digits, `-` and `_`")]
+    InvalidName,
+    #[error("Passwords need at least {MIN_PASSWORD_LENGTH} characters")]
+    PasswordTooShort,
+    #[error("This user name is taken")]
+    NameTaken,
+    #[error("The user name or password is incorrect")]
+    WrongCredentials,
+    #[error("Registration is disabled")]
+    RegistrationDisabled,
+    #[error("Too many failed logins, try again in {} minutes", .0.as_secs().div_ceil(60))]
+    TooManyAttempts(std::time::Duration),
+    #[error("The API key is invalid or was revoked")]
+    InvalidApiKey,
+    #[error("You need to log in first")]
+    NotLoggedIn,
+}
+
+impl ResponseError for AccountError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AccountError::InvalidName | AccountError::PasswordTooShort => StatusCode::BAD_REQUEST,
+            AccountError::NameTaken => StatusCode::CONFLICT,
+            AccountError::WrongCredentials
+            | AccountError::InvalidApiKey
+            | AccountError::NotLoggedIn => StatusCode::UNAUTHORIZED,
+            AccountError::RegistrationDisabled => StatusCode::FORBIDDEN,
+            AccountError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let AccountError::TooManyAttempts(wait) = self {
+            response.insert_header((header::RETRY_AFTER, wait.as_secs().to_string()));
+        }
+        response
+            .content_type(mime::TEXT_PLAIN_UTF_8)
+            .body(self.to_string())
+    }
+}
+
+/// Owner of the pastes of the user whose API key is sent as bearer token, an
+/// invalid key is an error instead of falling back to the cookie.
+async fn api_key_owner(request: &HttpRequest, database: &DB) -> Result<Option<String>> {
+    let Some(key) = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|it| it.to_str().ok())
+        .and_then(|it| it.strip_prefix("Bearer "))
+    else {
+        return Ok(None);
+    };
+    match database
+        .api_key_user(key.trim())
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        Some(name) => Ok(Some(db::User::owner(&name))),
+        None => Err(AccountError::InvalidApiKey.into()),
+    }
+}
+
+/// Owner of a new upload, the user of the API key or the one in the cookie.
+async fn uploader(
+    request: &HttpRequest,
+    database: &DB,
+    cookies: &mut CookieJar,
+    keys: &CookieKeys,
+) -> Result<String> {
+    Ok(match api_key_owner(request, database).await? {
+        Some(owner) => owner,
+        None => keys.owner_or_create(cookies),
+    })
+}
+
+/// User that is logged in with the cookie.
+async fn logged_in(
+    database: &DB,
+    cookies: &CookieJar,
+    keys: &CookieKeys,
+) -> Result<Option<db::User>> {
+    let Some(owner) = keys.owner(cookies) else {
+        return Ok(None);
+    };
+    let Some(name) = db::User::name_from_owner(&owner) else {
+        return Ok(None);
+    };
+    database.user(name).await.map_err(ErrorInternalServerError)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LENGTH).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[derive(Template)]
+#[template(path = "account.html")]
+struct Account {
+    /// Relative path to the root, the page is also shown for the forms it
+    /// submits.
+    root: &'static str,
+    user: Option<db::User>,
+    /// API key that was just created, it is only shown once.
+    new_key: Option<String>,
+    error: Option<AccountError>,
+    registration: bool,
+}
+
+impl Account {
+    fn error(
+        root: &'static str,
+        error: AccountError,
+        config: &Config,
+    ) -> CustomizeResponder<HttpResponse> {
+        let status = error.status_code();
+        Account {
+            root,
+            user: None,
+            new_key: None,
+            error: Some(error),
+            registration: config.registration,
+        }
+        .to_response()
+        .customize()
+        .with_status(status)
+    }
+}
+
+/// Redirect back to the account page, setting the changed cookies.
+fn to_account(root: &str, cookies: &CookieJar) -> CustomizeResponder<HttpResponse> {
+    HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("{root}account")))
+        .cookie_delta(cookies)
+        .finish()
+        .customize()
+}
+
+#[get("account")]
+async fn account(
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    Ok(Account {
+        root: "",
+        user: logged_in(&database, &cookies, &keys).await?,
+        new_key: None,
+        error: None,
+        registration: config.registration,
+    })
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    name: String,
+    password: String,
+}
+
+#[post("account/login")]
+async fn login(
+    web::Form(Credentials { name, password }): web::Form<Credentials>,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+    Cookies(mut cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let name = name.trim();
+    let key = format!("{LOGIN_ATTEMPTS}{name}");
+    if let Some(wait) = attempts.blocked(&key) {
+        return Ok(Account::error(
+            "../",
+            AccountError::TooManyAttempts(wait),
+            &config,
+        ));
+    }
+    let hash = database
+        .user(name)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map(|user| user.password);
+    let verified = web::block(move || password::verify_existing(&password, hash.as_deref()))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    if !verified {
+        attempts.failed(&key);
+        return Ok(Account::error(
+            "../",
+            AccountError::WrongCredentials,
+            &config,
+        ));
+    }
+    attempts.succeeded(&key);
+    log_in(name, &database, &mut cookies, &keys).await?;
+    Ok(to_account("../", &cookies))
+}
+
+#[post("account/register")]
+async fn register(
+    web::Form(Credentials { name, password }): web::Form<Credentials>,
+    database: Data<DB>,
+    Cookies(mut cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let name = name.trim();
+    let error = if !config.registration {
+        Some(AccountError::RegistrationDisabled)
+    } else if !is_valid_name(name) {
+        Some(AccountError::InvalidName)
+    } else if password.chars().count() < MIN_PASSWORD_LENGTH {
+        Some(AccountError::PasswordTooShort)
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return Ok(Account::error("../", error, &config));
+    }
+    let hash = web::block(move || password::hash(&password))
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?;
+    if !database
+        .create_user(name, hash)
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        return Ok(Account::error("../", AccountError::NameTaken, &config));
+    }
+    log_in(name, &database, &mut cookies, &keys).await?;
+    Ok(to_account("../", &cookies))
+}
+
+/// Sets the owner cookie to the account `name`, the pastes uploaded
+/// anonymously with this browser so far then belong to the account.
+async fn log_in(
+    name: &str,
+    database: &DB,
+    cookies: &mut CookieJar,
+    keys: &CookieKeys,
+) -> Result<()> {
+    let owner = db::User::owner(name);
+    if let Some(previous) = keys
+        .owner(cookies)
+        .filter(|previous| db::User::name_from_owner(previous).is_none())
+    {
+        database
+            .transfer_pastes(&previous, &owner)
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+    keys.set_owner(cookies, owner);
+    Ok(())
+}
+
+#[post("account/logout")]
+async fn logout(Cookies(mut cookies): Cookies, keys: Data<CookieKeys>) -> impl Responder {
+    keys.remove_owner(&mut cookies);
+    to_account("../", &cookies)
+}
+
+#[derive(Deserialize)]
+struct NewApiKey {
+    label: String,
+}
+
+#[post("account/keys")]
+async fn create_api_key(
+    web::Form(NewApiKey { label }): web::Form<NewApiKey>,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let Some(user) = logged_in(&database, &cookies, &keys).await? else {
+        return Ok(Account::error("../", AccountError::NotLoggedIn, &config));
+    };
+    let label = label.trim().chars().take(100).collect();
+    let new_key = database
+        .create_api_key(&user.name, label)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(Account {
+        root: "../",
+        user: database
+            .user(&user.name)
+            .await
+            .map_err(ErrorInternalServerError)?,
+        new_key,
+        error: None,
+        registration: config.registration,
+    }
+    .to_response()
+    .customize())
+}
+
+#[derive(Deserialize)]
+struct RevokeApiKey {
+    id: String,
+}
+
+#[post("account/keys/revoke")]
+async fn revoke_api_key(
+    web::Form(RevokeApiKey { id }): web::Form<RevokeApiKey>,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let Some(user) = logged_in(&database, &cookies, &keys).await? else {
+        return Ok(Account::error("../../", AccountError::NotLoggedIn, &config));
+    };
+    database
+        .revoke_api_key(&user.name, &id)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(to_account("../../", &cookies))
+}
+
+#[derive(Debug, thiserror::Error)]
+enum PastesError {
+    #[error("Only `owner=me` is supported")]
+    Owner,
+    #[error("Unknown action `{0}`")]
+    Action(String),
+}
+
+impl ResponseError for PastesError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum BulkAction {
+    /// Deletes the pastes immediately.
+    Delete,
+    /// Resets the expiry to `max_age` from now, permanent pastes stay so.
+    Extend,
+}
+
+impl str::FromStr for BulkAction {
+    type Err = PastesError;
+
+    fn from_str(action: &str) -> Result<Self, PastesError> {
+        match action {
+            "delete" => Ok(BulkAction::Delete),
+            "extend" => Ok(BulkAction::Extend),
+            action => Err(PastesError::Action(action.to_owned())),
+        }
+    }
+}
+
+/// Applies `action` to those `names` that are owned by `owner`.
+async fn bulk_action(
+    action: BulkAction,
+    names: &[String],
+    owner: &str,
+    database: &DB,
+    config: &Config,
+) -> Result<()> {
+    for name in names {
+        let Some(file) = database
+            .load_file(name)
+            .await
+            .map_err(ErrorInternalServerError)?
+        else {
+            continue;
+        };
+        if file.metadata().owner != owner {
+            continue;
+        }
+        match action {
+            BulkAction::Delete => database.delete_at(name, Utc::now()).await,
+            BulkAction::Extend => match file.metadata().delete_at {
+                Some(delete_at) if delete_at < Utc::now() + config.max_age => {
+                    database
+                        .set_expiry(name, Some(Utc::now() + config.max_age))
+                        .await
+                }
+                _ => continue,
+            },
+        }
+        .map_err(ErrorInternalServerError)?;
+    }
+    Ok(())
+}
+
+/// Owner of the API key or the cookie, without creating one.
+async fn current_owner(
+    request: &HttpRequest,
+    database: &DB,
+    cookies: &CookieJar,
+    keys: &CookieKeys,
+) -> Result<Option<String>> {
+    Ok(api_key_owner(request, database)
+        .await?
+        .or_else(|| keys.owner(cookies)))
+}
+
+async fn owned_pastes(owner: Option<&str>, database: &DB) -> Result<Vec<db::PasteInfo>> {
+    match owner {
+        Some(owner) => database
+            .owned_pastes(owner)
+            .await
+            .map_err(ErrorInternalServerError),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "mine.html")]
+struct Mine {
+    pastes: Vec<db::PasteInfo>,
+}
+
+#[get("mine")]
+async fn mine(
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    let owner = current_owner(&request, &database, &cookies, &keys).await?;
+    Ok(Mine {
+        pastes: owned_pastes(owner.as_deref(), &database).await?,
+    })
+}
+
+/// Bulk actions submitted by browsers, the form contains a `paste` field per
+/// selected paste.
+#[post("mine")]
+async fn post_mine(
+    body: Bytes,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let mut action = None;
+    let mut names = Vec::new();
+    for (key, value) in url::form_urlencoded::parse(&body) {
+        match &*key {
+            "action" => action = Some(value.parse::<BulkAction>()?),
+            "paste" => names.push(value.into_owned()),
+            _ => {}
+        }
+    }
+    let action = action.ok_or_else(|| PastesError::Action(String::new()))?;
+    if let Some(owner) = current_owner(&request, &database, &cookies, &keys).await? {
+        bulk_action(action, &names, &owner, &database, &config).await?;
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "mine"))
+        .finish())
+}
+
+#[derive(Deserialize)]
+struct PastesQuery {
+    owner: String,
+}
+
+#[get("api/pastes")]
+async fn api_pastes(
+    Query(PastesQuery { owner }): Query<PastesQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    if owner != "me" {
+        return Err(PastesError::Owner.into());
+    }
+    let owner = current_owner(&request, &database, &cookies, &keys).await?;
+    Ok(web::Json(owned_pastes(owner.as_deref(), &database).await?))
+}
+
+#[derive(Deserialize)]
+struct BulkRequest {
+    action: BulkAction,
+    pastes: Vec<String>,
+}
+
+#[post("api/pastes")]
+async fn api_bulk(
+    web::Json(BulkRequest { action, pastes }): web::Json<BulkRequest>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let owner = current_owner(&request, &database, &cookies, &keys).await?;
+    if let Some(owner) = &owner {
+        bulk_action(action, &pastes, owner, &database, &config).await?;
+    }
+    Ok(web::Json(owned_pastes(owner.as_deref(), &database).await?))
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ExpiryError {
+    #[error("Only the owner can change when this paste is deleted")]
+    NotOwner,
+    #[error("Only privileged accounts can keep pastes forever")]
+    NotPrivileged,
+    #[error("`expires_in` must be a number of seconds, `max` or `never`")]
+    Invalid,
+}
+
+impl ResponseError for ExpiryError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ExpiryError::NotOwner | ExpiryError::NotPrivileged => StatusCode::FORBIDDEN,
+            ExpiryError::Invalid => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExpiryForm {
+    /// Seconds from now, `max` for the configured `max_age` or `never`.
+    expires_in: String,
+}
+
+/// Extends or shortens the time until the paste is deleted, at most to
+/// `max_age` from now. Privileged accounts can also keep it forever.
+#[routes]
+#[post("expire/{id:[^/.]+}.{ext}")]
+#[post("expire/{id}")]
+async fn expire(
+    Path(file_name): Path<FileName>,
+    web::Form(ExpiryForm { expires_in }): web::Form<ExpiryForm>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let Some(file) = database
+        .load_file(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    let owner = current_owner(&request, &database, &cookies, &keys)
+        .await?
+        .filter(|owner| owner == &file.metadata().owner)
+        .ok_or(ExpiryError::NotOwner)?;
+    let delete_at = match expires_in.trim() {
+        "never" if config.is_admin(&owner) => None,
+        "never" => return Err(ExpiryError::NotPrivileged.into()),
+        "max" => Some(Utc::now() + config.max_age),
+        seconds => {
+            let expires_in = seconds
+                .parse::<i64>()
+                .ok()
+                .filter(|&seconds| seconds > 0)
+                .and_then(Duration::try_seconds)
+                .ok_or(ExpiryError::Invalid)?;
+            Some(Utc::now() + expires_in.min(config.max_age))
+        }
+    };
+    database
+        .set_expiry(&file_name.id, delete_at)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("../{file_name}")))
+        .finish()
+        .customize())
+}
+
+/// Cancels a deletion requested by someone other than the owner.
+#[routes]
+#[post("restore/{id:[^/.]+}.{ext}")]
+#[post("restore/{id}")]
+async fn restore(
+    Path(file_name): Path<FileName>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    // Already deleted pastes can not be restored.
+    let Some(file) = database
+        .load_file(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    current_owner(&request, &database, &cookies, &keys)
+        .await?
+        .filter(|owner| owner == &file.metadata().owner)
+        .ok_or(ExpiryError::NotOwner)?;
+    database
+        .cancel_deletion(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("../{file_name}")))
+        .finish()
+        .customize())
+}
+
+/// Prefix of the keys reports are counted under in [`Attempts`], followed by
+/// the hashed IP address.
+const REPORT_ATTEMPTS: &str = "report:";
+const MAX_REASON_LENGTH: usize = 1000;
+/// Characters of the paste shown in the list of reports.
+const PREVIEW_LENGTH: usize = 500;
+
+#[derive(Debug, thiserror::Error)]
+enum ReportError {
+    #[error("Please describe why you are reporting this paste")]
+    NoReason,
+    #[error("The reason can be at most {0} characters long")]
+    TooLong(usize),
+    #[error("Too many reports, try again in {} minutes", .0.as_secs().div_ceil(60))]
+    TooMany(std::time::Duration),
+    #[error("Only administrators can do this")]
+    NotAdmin,
+    #[error("Unknown action `{0}`")]
+    Action(String),
+}
+
+impl ResponseError for ReportError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ReportError::NoReason | ReportError::TooLong(_) | ReportError::Action(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            ReportError::TooMany(_) => StatusCode::TOO_MANY_REQUESTS,
+            ReportError::NotAdmin => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReportForm {
+    reason: String,
+}
+
+/// Address of the client sending `request`. Forwarded headers are only
+/// trusted from `trusted_proxies`, anyone else could set them.
+fn client_ip(request: &HttpRequest, config: &Config) -> String {
+    let Some(peer) = request.peer_addr().map(|addr| addr.ip()) else {
+        return String::new();
+    };
+    if config.trusted_proxies.contains(&peer) {
+        if let Some(ip) = request.connection_info().realip_remote_addr() {
+            return ip.to_owned();
+        }
+    }
+    peer.to_string()
+}
+
+#[routes]
+#[post("report/{id:[^/.]+}.{ext}")]
+#[post("report/{id}")]
+async fn report_paste(
+    Path(file_name): Path<FileName>,
+    web::Form(ReportForm { reason }): web::Form<ReportForm>,
+    request: HttpRequest,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    #[derive(Template)]
+    #[template(path = "reported.html")]
+    struct Reported {
+        file_name: FileName,
+    }
+
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(ReportError::NoReason.into());
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(ReportError::TooLong(MAX_REASON_LENGTH).into());
+    }
+    if database
+        .load_file(&file_name.id)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .is_none()
+    {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    }
+    let reporter = keys.hash_ip(&client_ip(&request, &config));
+    // Every report counts, not only failed ones.
+    let key = format!("{REPORT_ATTEMPTS}{reporter}");
+    if let Some(wait) = attempts.blocked(&key) {
+        return Err(ReportError::TooMany(wait).into());
+    }
+    attempts.failed(&key);
+    database
+        .add_report(&file_name.id, reason.to_owned(), reporter)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(Reported { file_name }.to_response().customize())
+}
+
+/// Fails unless the requester is logged into an account listed in `admins`.
+async fn require_admin(
+    request: &HttpRequest,
+    database: &DB,
+    cookies: &CookieJar,
+    keys: &CookieKeys,
+    config: &Config,
+) -> Result<()> {
+    match current_owner(request, database, cookies, keys).await? {
+        Some(owner) if config.is_admin(&owner) => Ok(()),
+        _ => Err(ReportError::NotAdmin.into()),
+    }
+}
+
+enum Preview {
+    /// The paste was deleted since it was reported.
+    Deleted,
+    Text(String),
+    Binary(u64),
+    Files(Vec<String>),
+    Encrypted,
+}
+
+async fn preview(name: &str, database: &DB) -> Result<Preview> {
+    let Some(file) = database
+        .load_file(name)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(Preview::Deleted);
+    };
+    if file.metadata().encrypted {
+        return Ok(Preview::Encrypted);
+    }
+    if !file.metadata().files.is_empty() {
+        return Ok(Preview::Files(file.metadata().files.clone()));
+    }
+    let mut contents = file.contents().await.map_err(ErrorInternalServerError)?;
+    let len = contents.len();
+    // Enough for the preview, characters are at most four bytes long.
+    let limit = PREVIEW_LENGTH * 4;
+    let mut data = Vec::new();
+    while data.len() < limit {
+        let Some(block) = contents
+            .try_next()
+            .await
+            .map_err(ErrorInternalServerError)?
+        else {
+            break;
+        };
+        data.extend_from_slice(&block);
+    }
+    data.truncate(limit);
+    let text = match str::from_utf8(&data) {
+        Ok(text) => text,
+        // The last character was cut off.
+        Err(error) if error.error_len().is_none() && (data.len() as u64) < len => {
+            str::from_utf8(&data[..error.valid_up_to()]).expect("valid up to here")
+        }
+        Err(_) => return Ok(Preview::Binary(len)),
+    };
+    Ok(Preview::Text(text.chars().take(PREVIEW_LENGTH).collect()))
+}
+
+struct ReportEntry {
+    id: u64,
+    report: db::Report,
+    preview: Preview,
+}
+
+#[get("admin/reports")]
+async fn admin_reports(
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    #[derive(Template)]
+    #[template(path = "reports.html")]
+    struct Reports {
+        reports: Vec<ReportEntry>,
+    }
+
+    require_admin(&request, &database, &cookies, &keys, &config).await?;
+    let mut reports = Vec::new();
+    for (id, report) in database.reports().await.map_err(ErrorInternalServerError)? {
+        reports.push(ReportEntry {
+            id,
+            preview: preview(&report.paste, &database).await?,
+            report,
+        });
+    }
+    Ok(Reports { reports })
+}
+
+#[derive(Deserialize)]
+struct ModerationForm {
+    id: u64,
+    action: String,
+}
+
+/// Handles a report by deleting the paste, additionally blocking its
+/// contents, or dismissing the report.
+#[post("admin/reports")]
+async fn moderate(
+    web::Form(ModerationForm { id, action }): web::Form<ModerationForm>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    require_admin(&request, &database, &cookies, &keys, &config).await?;
+    let Some(report) = database
+        .report(id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "reports"))
+            .finish());
+    };
+    match action.as_str() {
+        "dismiss" => database.delete_report(id).await,
+        "delete" | "block" => {
+            if action == "block" {
+                for hash in database
+                    .content_hashes(&report.paste)
+                    .await
+                    .map_err(ErrorInternalServerError)?
+                {
+                    database
+                        .block_hash(hash, report.reason.clone())
+                        .await
+                        .map_err(ErrorInternalServerError)?;
+                }
+            }
+            database
+                .delete_at(&report.paste, Utc::now())
+                .await
+                .map_err(ErrorInternalServerError)?;
+            database.delete_reports_of(&report.paste).await
+        }
+        action => return Err(ReportError::Action(action.to_owned()).into()),
+    }
+    .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "reports"))
+        .finish())
+}
+
+/// Lists the blocked content hashes in the format accepted by
+/// [`import_blocklist`].
+#[get("admin/blocklist")]
+async fn get_blocklist(
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    require_admin(&request, &database, &cookies, &keys, &config).await?;
+    let blocked = database
+        .blocked_hashes()
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type(mime::TEXT_PLAIN_UTF_8)
+        .body(blocklist::format(&blocked)))
+}
+
+/// Adds the hashes of a list in the format described in [`blocklist`].
+#[post("admin/blocklist")]
+async fn import_blocklist(
+    list: String,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    require_admin(&request, &database, &cookies, &keys, &config).await?;
+    let added = blocklist::import(blocklist::parse(&list)?, &database)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(format!("Blocked {added} new hashes\n"))
+}
+
+#[delete("admin/blocklist/{hash}")]
+async fn unblock(
+    Path(hash): Path<String>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    require_admin(&request, &database, &cookies, &keys, &config).await?;
+    if database
+        .unblock_hash(&hash.to_ascii_lowercase())
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum EditError {
+    #[error("Only the owner can edit this paste")]
+    NotOwner,
+    #[error("Multi-file pastes can not be edited")]
+    MultiFile,
+    #[error("Only text can be edited in the browser")]
+    NotText,
+    #[error("Encrypted pastes can not be edited or forked")]
+    Encrypted,
+}
+
+impl ResponseError for EditError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            EditError::NotOwner => StatusCode::FORBIDDEN,
+            EditError::MultiFile | EditError::NotText | EditError::Encrypted => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+        }
+    }
+}
+
+/// Loads the paste `id` if it can be edited by the requester, the owner is
+/// identified by the API key, the cookie or the delete `token` of the paste.
+async fn editable(
+    id: &str,
+    token: Option<&str>,
+    request: &HttpRequest,
+    database: &DB,
+    cookies: &CookieJar,
+    keys: &CookieKeys,
+) -> Result<Option<File>> {
+    let Some(file) = database
+        .load_file(id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(None);
+    };
+    let owner = &file.metadata().owner;
+    let requester = api_key_owner(request, database)
+        .await?
+        .or_else(|| keys.owner(cookies));
+    if requester.as_ref() != Some(owner)
+        && !token.is_some_and(|token| keys.verify_owner_token(owner, id, token))
+    {
+        return Err(EditError::NotOwner.into());
+    }
+    if !file.metadata().files.is_empty() {
+        return Err(EditError::MultiFile.into());
+    }
+    if file.metadata().encrypted {
+        return Err(EditError::Encrypted.into());
+    }
+    Ok(Some(file))
+}
+
+#[routes]
+#[get("edit/{id:[^/.]+}.{ext}")]
+#[get("edit/{id}")]
+async fn edit(
+    Path(FileName { id, ext }): Path<FileName>,
+    Query(DeleteQuery { token }): Query<DeleteQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    let Some(file) = editable(&id, token.as_deref(), &request, &database, &cookies, &keys).await?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    let code = file
+        .contents()
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_string()
+        .await
+        .map_err(|_| EditError::NotText)?;
+
+    Ok(Upload {
+        code,
+        extension: ext.unwrap_or_default(),
+        edit: Some(id),
+        token,
+        parent: None,
+    }
+    .to_response()
+    .customize())
+}
+
+#[derive(Deserialize)]
+struct ForkQuery {
+    /// Revision to fork instead of the current one.
+    rev: Option<usize>,
+}
+
+/// Upload form prefilled with the contents of a paste.
+#[routes]
+#[get("fork/{id:[^/.]+}.{ext}")]
+#[get("fork/{id}")]
+async fn fork(
+    Path(file_name): Path<FileName>,
+    Query(ForkQuery { rev }): Query<ForkQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+) -> Result<impl Responder> {
+    let file = if let Some(rev) = rev {
+        database.load_revision(&file_name.id, rev).await
+    } else {
+        database.load_file(&file_name.id).await
+    }
+    .map_err(ErrorInternalServerError)?;
+    let Some(file) = file else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    check_paste_password(&file_name.id, &request, &database, &attempts).await??;
+    if !file.metadata().files.is_empty() {
+        return Err(EditError::MultiFile.into());
+    }
+    if file.metadata().encrypted {
+        return Err(EditError::Encrypted.into());
+    }
+    let code = file
+        .contents()
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_string()
+        .await
+        .map_err(|_| EditError::NotText)?;
+
+    Ok(Upload {
+        code,
+        parent: Some(file_name.to_string()),
+        extension: file_name.ext.unwrap_or_default(),
+        edit: None,
+        token: None,
+    }
+    .to_response()
+    .customize())
+}
+
+#[post("edit/{id}", guard = "is_form")]
+async fn post_edit(
+    Path(id): Path<String>,
+    Query(DeleteQuery { token }): Query<DeleteQuery>,
+    payload: Multipart,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+) -> Result<impl Responder> {
+    if editable(&id, token.as_deref(), &request, &database, &cookies, &keys)
+        .await?
+        .is_none()
+    {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    }
+
+    let mut multipart = payload;
+    let mut extension = None;
+    let mut data = None;
+    while let Some(field) = multipart.try_next().await? {
+        match field.name().unwrap_or_default() {
+            "data" => data = Some(read_limited(field).await?),
+            "extension" => {
+                let buf = text_field(field, "extension", 20).await?;
+                if !buf.is_empty() {
+                    extension = Some(buf);
+                }
+            }
+            name => {
+                let name = name.to_string();
+                return Err(UploadError::InvalidField(name).into());
+            }
+        }
+    }
+    let data = data
+        .filter(|it| !it.is_empty())
+        .ok_or(UploadError::NoData)?;
+    check_blocklist(&data, &database).await?;
+
+    database
+        .new_revision(&id, &data)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    strip_metadata(&id, &database, true).await?;
+
+    let name = id + &extension.map(|e| format!(".{e}")).unwrap_or_default();
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("../{name}")))
+        .finish()
+        .customize())
+}
+
+/// Submits a new revision of a paste.
+#[routes]
+#[put("{id:[^/.]+}.{ext}")]
+#[put("{id}")]
+#[allow(clippy::too_many_arguments)]
+async fn put_raw(
+    Path(file_name): Path<FileName>,
+    payload: Payload,
+    Query(query): Query<UploadQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let token = query.token.as_deref();
+    if editable(&file_name.id, token, &request, &database, &cookies, &keys)
+        .await?
+        .is_none()
+    {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    }
+    let data = read_limited(payload).await?;
+    if data.is_empty() {
+        return Err(UploadError::NoData.into());
+    }
+    check_blocklist(&data, &database).await?;
+
+    let revision = database
+        .new_revision(&file_name.id, &data)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .unwrap_or_default();
+    strip_metadata(
+        &file_name.id,
+        &database,
+        query.strip_metadata.unwrap_or(true),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .body(format!("{}{file_name}/rev/{revision}\n", base_url(&config)))
+        .customize())
+}
+
+#[derive(Deserialize)]
+struct RevisionPath {
+    id: String,
+    ext: Option<String>,
+    rev: usize,
+}
+
+#[routes]
+#[get("{id:[^/.]+}.{ext}/rev/{rev}")]
+#[get("{id}/rev/{rev}")]
+async fn raw_revision(
+    Path(RevisionPath { id, ext, rev }): Path<RevisionPath>,
+    request: HttpRequest,
+    database: Data<DB>,
+    attempts: Data<Attempts>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let content_type = content_type(ext.as_deref());
+    let Some(paste) = database
+        .load_file(&id)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    if let Some(redirect) =
+        raw_redirect(&id, paste.metadata(), &request, &config, &attempts).await?
+    {
+        return Ok(redirect.customize());
+    }
+    let Some(file) = database
+        .load_revision(&id, rev)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(NotFound
+            .to_response()
+            .customize()
+            .with_status(StatusCode::NOT_FOUND));
+    };
+    check_password(&id, paste.metadata(), &request, &attempts).await??;
+    Ok(raw_response(content_type)
+        .streaming(
+            file.contents()
+                .await
+                .map_err(ErrorInternalServerError)?
+                .map_ok(From::from),
+        )
+        .customize())
+}
+
+/// A paste compared in a diff, `id[.ext][@revision]`.
+struct PasteRef {
+    file_name: FileName,
+    rev: Option<usize>,
+}
+
+impl PasteRef {
+    fn parse(value: &str) -> Result<Self, DiffError> {
+        let invalid = || DiffError::Invalid(value.to_owned());
+        let (name, rev) = match value.rsplit_once('@') {
+            Some((name, rev)) => (name, Some(rev.parse().map_err(|_| invalid())?)),
+            None => (value, None),
+        };
+        let (id, ext) = match name.split_once('.') {
+            Some((id, ext)) => (id, Some(ext.to_owned())),
+            None => (name, None),
+        };
+        if id.is_empty() || ext.as_deref() == Some("") || rev == Some(0) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            file_name: FileName {
+                id: id.to_owned(),
+                ext,
+            },
+            rev,
+        })
+    }
+
+    /// Link to the view of this revision, relative to the root.
+    fn link(&self) -> String {
+        match self.rev {
+            Some(rev) => format!("{}?rev={rev}", self.file_name),
+            None => self.file_name.to_string(),
+        }
+    }
+
+    /// Loads the text of the referenced revision, failing for files larger
+    /// than `limit`.
+    async fn load(
+        &self,
+        request: &HttpRequest,
+        database: &DB,
+        attempts: &Attempts,
+        limit: u64,
+    ) -> Result<String> {
+        check_paste_password(&self.file_name.id, request, database, attempts).await??;
+        let file = if let Some(rev) = self.rev {
+            database.load_revision(&self.file_name.id, rev).await
+        } else {
+            database.load_file(&self.file_name.id).await
+        }
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| DiffError::NotFound(self.to_string()))?;
+        if !file.metadata().files.is_empty() {
+            return Err(DiffError::MultiFile(self.to_string()).into());
+        }
+        if file.metadata().encrypted {
+            return Err(DiffError::Encrypted(self.to_string()).into());
+        }
+        let contents = file.contents().await.map_err(ErrorInternalServerError)?;
+        if contents.len() >= limit {
+            return Err(DiffError::TooLarge(self.to_string()).into());
+        }
+        Ok(contents
+            .into_string()
             .await
-            .map_err(ErrorInternalServerError)?;
+            .map_err(|_| DiffError::NotText(self.to_string()))?)
     }
+}
 
-    Ok(HttpResponse::SeeOther()
-        .append_header((header::LOCATION, "/"))
-        .finish())
+impl Display for PasteRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_name)?;
+        if let Some(rev) = self.rev {
+            write!(f, "@{rev}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DiffError {
+    #[error("`{0}` is not a valid paste, expected `id`, `id.ext` or `id.ext@revision`")]
+    Invalid(String),
+    #[error("Paste `{0}` does not exist")]
+    NotFound(String),
+    #[error("Paste `{0}` has multiple files and can not be compared")]
+    MultiFile(String),
+    #[error("Paste `{0}` is not a text file")]
+    NotText(String),
+    #[error("Paste `{0}` is encrypted and can only be compared after decrypting it")]
+    Encrypted(String),
+    #[error("Paste `{0}` is too large to be compared")]
+    TooLarge(String),
+}
+
+impl ResponseError for DiffError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DiffError::Invalid(_) => StatusCode::BAD_REQUEST,
+            DiffError::NotFound(_) => StatusCode::NOT_FOUND,
+            DiffError::MultiFile(_) | DiffError::NotText(_) | DiffError::Encrypted(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            DiffError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DiffPath {
+    a: String,
+    b: String,
+}
+
+/// Loads both sides of a diff, the syntax is taken from the extension of the
+/// new side or else the old one.
+async fn diff_sides(
+    DiffPath { a, b }: DiffPath,
+    request: &HttpRequest,
+    database: &DB,
+    attempts: &Attempts,
+    limit: u64,
+) -> Result<(PasteRef, String, PasteRef, String, Option<String>)> {
+    let (a, b) = (PasteRef::parse(&a)?, PasteRef::parse(&b)?);
+    let (old, new) = (
+        a.load(request, database, attempts, limit).await?,
+        b.load(request, database, attempts, limit).await?,
+    );
+    let ext = b.file_name.ext.clone().or_else(|| a.file_name.ext.clone());
+    Ok((a, old, b, new, ext))
+}
+
+#[derive(Deserialize)]
+struct DiffQuery {
+    /// `split` for a side-by-side view.
+    view: Option<String>,
+    color: Option<String>,
+}
+
+/// Unified diff of two pastes for non browser clients.
+#[get("diff/{a}/{b}", guard = "no_browser")]
+async fn raw_diff(
+    Path(path): Path<DiffPath>,
+    Query(query): Query<DiffQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    syntaxes: Data<SyntaxSet>,
+    theme: Data<Theme>,
+    attempts: Data<Attempts>,
+) -> Result<impl Responder> {
+    let (a, old, b, new, _) =
+        diff_sides(path, &request, &database, &attempts, FILE_LIMIT as u64).await?;
+    let unified = web::block(move || diff::unified((&a.to_string(), &old), (&b.to_string(), &new)))
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    if let (Some(colors), Some(syntax)) = (
+        colors(query.color.as_deref(), &request),
+        syntaxes.find_syntax_by_token("diff"),
+    ) {
+        if let Ok(highlighted) = terminal::highlight(&unified, syntax, &syntaxes, &theme, colors) {
+            return Ok(HttpResponse::Ok()
+                .content_type("text/x-ansi; charset=utf-8")
+                .body(highlighted));
+        }
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("text/x-diff; charset=utf-8")
+        .body(unified))
+}
+
+#[get("diff/{a}/{b}")]
+async fn diff_view(
+    Path(path): Path<DiffPath>,
+    Query(query): Query<DiffQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    syntaxes: Data<SyntaxSet>,
+    attempts: Data<Attempts>,
+) -> Result<impl Responder> {
+    #[derive(Template)]
+    #[template(path = "diff.html")]
+    struct Diff {
+        diffs: Vec<FileDiff>,
+        split: bool,
+        a: PasteRef,
+        b: PasteRef,
+    }
+
+    let (a, old, b, new, ext) =
+        diff_sides(path, &request, &database, &attempts, MAX_DISPLAY_SIZE).await?;
+    let diff = web::block({
+        let (old_name, new_name) = (a.to_string(), b.to_string());
+        move || {
+            let syntax = ext.and_then(|ext| syntaxes.find_syntax_by_token(&ext));
+            diff::texts((&old_name, &old), (&new_name, &new), syntax, &syntaxes)
+        }
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(Diff {
+        diffs: vec![diff],
+        split: query.view.as_deref() == Some("split"),
+        a,
+        b,
+    })
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -399,43 +2843,49 @@ enum UploadError {
     InvalidField(String),
     #[error("No text or file")]
     NoData,
+    #[error("Encrypted pastes must consist of a single encrypted envelope, see the README")]
+    NotEncrypted,
+    #[error("This content was taken down and can not be uploaded again")]
+    Blocked,
 }
 
 impl ResponseError for UploadError {
     fn status_code(&self) -> actix_web::http::StatusCode {
-        StatusCode::BAD_REQUEST
+        match self {
+            UploadError::Blocked => StatusCode::FORBIDDEN,
+            _ => StatusCode::BAD_REQUEST,
+        }
     }
 }
 
-async fn create_file<E: ResponseError + 'static>(
-    mut data: impl Stream<Item = Result<Bytes, E>> + Unpin,
-    database: &Data<DB>,
-    cookies: &mut CookieJar,
-    config: &Data<Config>,
-) -> Result<String> {
-    let owner = if let Some(owner) = cookies.get(OWNER_COOKIE) {
-        owner.value().to_owned()
-    } else {
-        let owner = Alphanumeric.sample_string(&mut rand::thread_rng(), 20);
-        cookies.add(Cookie::new(OWNER_COOKIE, owner.clone()));
-        owner
-    };
-
-    let file = database
-        .new_file(owner, Some(config.max_age))
-        .await
-        .map_err(ErrorInternalServerError)?;
+/// Maximum size of a paste, for multi-file pastes of all files combined.
+const FILE_LIMIT: usize = 10_000_000;
 
-    const FILE_LIMIT: usize = 10_000_000;
-    let mut limit: usize = FILE_LIMIT;
+/// Appends `data` to `file`, counting its length against `limit`. Fails if
+/// `file` stays empty or its contents are on the blocklist.
+async fn write_file<E: ResponseError + 'static>(
+    file: &File,
+    mut data: impl Stream<Item = Result<Bytes, E>> + Unpin,
+    database: &DB,
+    limit: &mut usize,
+) -> Result<()> {
+    let mut hash = Sha256::new();
     while let Some(data) = data.try_next().await? {
         if let Some(l) = limit.checked_sub(data.len()) {
-            limit = l;
+            *limit = l;
         } else {
             return Err(UploadError::FieldTooBig("file", FILE_LIMIT).into());
         }
+        hash.update(&data);
         file.append(&data).await.map_err(ErrorInternalServerError)?;
     }
+    if database
+        .is_blocked(&format!("{:x}", hash.finalize()))
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        return Err(UploadError::Blocked.into());
+    }
 
     if file
         .contents()
@@ -443,13 +2893,86 @@ async fn create_file<E: ResponseError + 'static>(
         .map_err(ErrorInternalServerError)?
         .is_empty()
     {
-        file.delete().await.map_err(ErrorInternalServerError)?;
         return Err(UploadError::NoData.into());
     }
+    Ok(())
+}
+
+/// Deletes the partially uploaded paste `name` if `result` is an error.
+async fn discard_failed<T>(result: Result<T>, name: Option<&str>, database: &DB) -> Result<T> {
+    if let (Err(_), Some(name)) = (&result, name) {
+        database
+            .delete(name)
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+    result
+}
 
+/// Creates a paste protected with the password `hash` from the start.
+async fn create_file<E: ResponseError + 'static>(
+    data: impl Stream<Item = Result<Bytes, E>> + Unpin,
+    database: &Data<DB>,
+    owner: &str,
+    hash: Option<String>,
+    config: &Data<Config>,
+    limit: &mut usize,
+) -> Result<String> {
+    let file = database
+        .new_file(owner.to_owned(), Some(config.max_age), hash)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let result = write_file(&file, data, database, limit).await;
+    discard_failed(result, Some(file.name()), database).await?;
     Ok(file.name().to_string())
 }
 
+async fn create_group_file<E: ResponseError + 'static>(
+    data: impl Stream<Item = Result<Bytes, E>> + Unpin,
+    database: &Data<DB>,
+    name: &str,
+    file_name: &str,
+    limit: &mut usize,
+) -> Result<()> {
+    let file = database
+        .new_group_file(name, file_name)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    write_file(&file, data, database, limit).await
+}
+
+/// Turns a user provided file name into a unique name for a file of a
+/// multi-file paste, unnamed files are numbered.
+fn group_file_name(file_name: Option<&str>, files: &[String]) -> String {
+    let file_name: String = file_name
+        .and_then(|it| it.rsplit(['/', '\\']).next())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(100)
+        .collect();
+    let file_name = match file_name.trim() {
+        "" | "." | ".." => format!("file{}", files.len() + 1),
+        file_name => file_name.to_owned(),
+    };
+    let mut unique = file_name.clone();
+    let mut n = 1;
+    while files.contains(&unique) {
+        n += 1;
+        unique = format!("{n}-{file_name}");
+    }
+    unique
+}
+
+/// `base_url` ending in a `/`, or empty.
+fn base_url(config: &Config) -> String {
+    if !config.base_url.is_empty() && !config.base_url.ends_with('/') {
+        format!("{}{}", config.base_url, "/")
+    } else {
+        config.base_url.to_string()
+    }
+}
+
 fn response(
     name: String,
     cookies: CookieJar,
@@ -460,26 +2983,143 @@ fn response(
     HttpResponse::SeeOther()
         .append_header((header::LOCATION, name.clone()))
         .cookie_delta(&cookies)
-        .body(format!(
-            "{}{name}\n",
-            if !config.base_url.is_empty() && !config.base_url.ends_with('/') {
-                format!("{}{}", config.base_url, "/")
-            } else {
-                config.base_url.to_string()
-            }
-        ))
+        .body(format!("{}{name}\n", base_url(config)))
+}
+
+/// Removes metadata like EXIF from uploaded images if `strip`, see
+/// [`images::strip_metadata`]. Images are rejected if they are on the
+/// blocklist without their metadata, as blocked pastes are stored stripped.
+async fn strip_metadata(name: &str, database: &Data<DB>, strip: bool) -> Result<()> {
+    let Some(file) = database
+        .load_file(name)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(());
+    };
+    let data = file
+        .contents()
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_vec()
+        .await
+        .map_err(ErrorInternalServerError)?;
+    if let Some(stripped) = web::block(move || {
+        images::strip_metadata(&data).filter(|stripped| stripped.len() != data.len())
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    {
+        check_blocklist(&stripped, database).await?;
+        if strip {
+            database
+                .replace_contents(name, &stripped)
+                .await
+                .map_err(ErrorInternalServerError)?;
+        }
+    }
+    Ok(())
+}
+
+async fn hash_password(password: String) -> Result<String> {
+    web::block(move || password::hash(password.trim()))
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)
+}
+
+/// Lets the uploader view the paste `name` protected with `hash` without
+/// entering the password.
+fn remember_password(name: &str, hash: &str, cookies: &mut CookieJar, keys: &CookieKeys) {
+    if let Some(cookie) = unlock_cookie(name, hash, keys.secure()) {
+        cookies.add(cookie);
+    }
+}
+
+/// Hash of the password to protect an upload with, sent in
+/// [`PASSWORD_HEADER`].
+async fn upload_password(request: &HttpRequest) -> Result<Option<String>> {
+    let password = request
+        .headers()
+        .get(PASSWORD_HEADER)
+        .and_then(|it| it.to_str().ok())
+        .map(str::trim)
+        .filter(|it| !it.is_empty());
+    match password {
+        Some(password) => Ok(Some(hash_password(password.to_owned()).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Marks `name` as encrypted by the client, after checking that it contains an
+/// envelope as described in [`encrypted`].
+async fn mark_encrypted(name: &str, database: &DB) -> Result<()> {
+    let Some(file) = database
+        .load_file(name)
+        .await
+        .map_err(ErrorInternalServerError)?
+    else {
+        return Ok(());
+    };
+    let data = file
+        .contents()
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_vec()
+        .await
+        .map_err(ErrorInternalServerError)?;
+    if !encrypted::is_valid(&data) {
+        file.delete().await.map_err(ErrorInternalServerError)?;
+        return Err(UploadError::NotEncrypted.into());
+    }
+    database
+        .set_encrypted(name)
+        .await
+        .map_err(ErrorInternalServerError)
+}
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    strip_metadata: Option<bool>,
+    /// The data is encrypted by the client.
+    #[serde(default)]
+    encrypted: bool,
+    /// Delete token of the paste a revision is submitted for, see [`editable`].
+    token: Option<String>,
 }
 
 #[post("/")]
 async fn post_raw(
     payload: Payload,
+    Query(query): Query<UploadQuery>,
+    request: HttpRequest,
     database: Data<DB>,
     Cookies(mut cookies): Cookies,
+    keys: Data<CookieKeys>,
     config: Data<Config>,
 ) -> Result<impl Responder> {
-    create_file(payload, &database, &mut cookies, &config)
-        .await
-        .map(|it| response(it, cookies, None, &config))
+    let owner = uploader(&request, &database, &mut cookies, &keys).await?;
+    let password = upload_password(&request).await?;
+    let mut limit = FILE_LIMIT;
+    let name = create_file(
+        payload,
+        &database,
+        &owner,
+        password.clone(),
+        &config,
+        &mut limit,
+    )
+    .await?;
+    if query.encrypted {
+        mark_encrypted(&name, &database).await?;
+    } else {
+        let stripped = strip_metadata(&name, &database, query.strip_metadata.unwrap_or(true)).await;
+        discard_failed(stripped, Some(&name), &database).await?;
+    }
+    if let Some(hash) = &password {
+        remember_password(&name, hash, &mut cookies, &keys);
+    }
+    Ok(response(name, cookies, None, &config))
 }
 
 fn is_form(it: &GuardContext) -> bool {
@@ -491,21 +3131,58 @@ fn is_form(it: &GuardContext) -> bool {
 #[post("/", guard = "is_form")]
 async fn post_form(
     payload: Multipart,
+    request: HttpRequest,
     database: Data<DB>,
     Cookies(mut cookies): Cookies,
+    keys: Data<CookieKeys>,
     config: Data<Config>,
 ) -> Result<impl Responder> {
-    let mut multipart = payload;
-    let mut extension = None;
+    let owner = uploader(&request, &database, &mut cookies, &keys).await?;
     let mut file = None;
+    let result = store_form(
+        payload,
+        &owner,
+        &mut file,
+        &database,
+        &mut cookies,
+        &keys,
+        &config,
+    )
+    .await;
+    // Everything uploaded so far is deleted if a later field fails.
+    let (file, extension) = discard_failed(result, file.as_deref(), &database).await?;
+    Ok(response(file, cookies, extension, &config))
+}
+
+/// Stores the fields of a form upload, `file` is set once the paste exists.
+/// Returns the paste and its extension.
+async fn store_form(
+    mut multipart: Multipart,
+    owner: &str,
+    file: &mut Option<String>,
+    database: &Data<DB>,
+    cookies: &mut CookieJar,
+    keys: &CookieKeys,
+    config: &Data<Config>,
+) -> Result<(String, Option<String>)> {
+    let mut extension = None;
+    let mut strip = true;
+    let mut parent = None;
+    let mut password = None;
+    // The paste is created locked if the password is not known yet.
+    let mut update_password = false;
+    let mut encrypted = false;
+    let mut files = Vec::new();
+    let mut limit = FILE_LIMIT;
 
-    while let Some(mut field) = multipart.try_next().await? {
+    while let Some(field) = multipart.try_next().await? {
         match field.name().unwrap_or_default() {
             "data" => {
-                if let Some(file_name) = field
+                let file_name = field
                     .content_disposition()
                     .and_then(ContentDisposition::get_filename)
-                {
+                    .map(str::to_owned);
+                if let Some(file_name) = &file_name {
                     if file_name.contains('.') {
                         let ext = file_name
                             .split('.')
@@ -517,22 +3194,60 @@ async fn post_form(
                         }
                     }
                 }
-                file = Some(create_file(field, &database, &mut cookies, &config).await?);
+                let group_name = group_file_name(file_name.as_deref(), &files);
+                if let Some(file) = file {
+                    // Multiple files are stored as a multi-file paste.
+                    if files.len() == 1 {
+                        database
+                            .make_group(file, &files[0])
+                            .await
+                            .map_err(ErrorInternalServerError)?;
+                    }
+                    create_group_file(field, database, file, &group_name, &mut limit).await?;
+                } else {
+                    update_password = password.is_none();
+                    let hash = password
+                        .clone()
+                        .unwrap_or_else(|| password::LOCKED.to_owned());
+                    *file = Some(
+                        create_file(field, database, owner, Some(hash), config, &mut limit).await?,
+                    );
+                }
+                files.push(group_name);
             }
             "extension" => {
-                let mut buf = String::new();
-                while let Some(data) = field.try_next().await? {
-                    buf += str::from_utf8(&data)?;
-                    if buf.len() > 20 {
-                        field.for_each(|_| ready(())).await;
-                        return Err(UploadError::FieldTooBig("extension", 20).into());
-                    }
+                let buf = text_field(field, "extension", 20).await?;
+                if !buf.is_empty() {
+                    extension = Some(buf);
+                }
+            }
+            "parent" => {
+                let buf = text_field(field, "parent", 50).await?;
+                if !buf.is_empty() {
+                    parent = Some(buf);
                 }
-                let buf = buf.trim();
+            }
+            "password" => {
+                let buf = text_field(field, "password", 200).await?;
                 if !buf.is_empty() {
-                    extension = Some(buf.to_owned());
+                    password = Some(hash_password(buf).await?);
+                    update_password |= file.is_some();
                 }
             }
+            "encrypted" => {
+                encrypted = match text_field(field, "encrypted", 5).await?.as_str() {
+                    "on" | "true" => true,
+                    "off" | "false" => false,
+                    _ => return Err(UploadError::InvalidField("encrypted".into()).into()),
+                };
+            }
+            "strip_metadata" => {
+                strip = match text_field(field, "strip_metadata", 5).await?.as_str() {
+                    "on" | "true" => true,
+                    "off" | "false" => false,
+                    _ => return Err(UploadError::InvalidField("strip_metadata".into()).into()),
+                };
+            }
             name => {
                 let name = name.to_string();
                 return Err(UploadError::InvalidField(name).into());
@@ -540,10 +3255,195 @@ async fn post_form(
         }
     }
 
-    Ok(response(
-        file.ok_or(UploadError::NoData)?,
-        cookies,
-        extension,
-        &config,
-    ))
+    let file = file.clone().ok_or(UploadError::NoData)?;
+    if let Some(parent) = parent {
+        // Only link to pastes that exist.
+        let id = parent.split('.').next().unwrap_or_default();
+        if database
+            .load_file(id)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .is_some()
+        {
+            database
+                .set_parent(&file, parent)
+                .await
+                .map_err(ErrorInternalServerError)?;
+        }
+    }
+    if encrypted {
+        if files.len() > 1 {
+            return Err(UploadError::NotEncrypted.into());
+        }
+        mark_encrypted(&file, database).await?;
+    }
+    if update_password {
+        database
+            .set_password(&file, password.clone())
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+    if let Some(hash) = &password {
+        remember_password(&file, hash, cookies, keys);
+    }
+    if files.len() > 1 {
+        for name in &files {
+            strip_metadata(&format!("/{file}/{name}"), database, strip).await?;
+        }
+        database
+            .set_group_files(&file, files)
+            .await
+            .map_err(ErrorInternalServerError)?;
+        return Ok((file, None));
+    }
+    if !encrypted {
+        strip_metadata(&file, database, strip).await?;
+    }
+    if let Some(extension) = &extension {
+        database
+            .set_extension(&file, extension.clone())
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+    Ok((file, extension))
+}
+
+#[derive(Deserialize)]
+struct JsonFile {
+    name: String,
+    content: String,
+}
+
+fn is_json(it: &GuardContext) -> bool {
+    it.header::<header::ContentType>()
+        .is_some_and(|it| it.0.essence_str() == mime::APPLICATION_JSON)
+}
+
+/// Creates a multi-file paste from a JSON array of files, other JSON is stored
+/// as a single `.json` file.
+#[post("/", guard = "is_json")]
+async fn post_json(
+    payload: Payload,
+    Query(query): Query<UploadQuery>,
+    request: HttpRequest,
+    database: Data<DB>,
+    Cookies(mut cookies): Cookies,
+    keys: Data<CookieKeys>,
+    config: Data<Config>,
+) -> Result<impl Responder> {
+    let owner = uploader(&request, &database, &mut cookies, &keys).await?;
+    let password = upload_password(&request).await?;
+    let body = read_limited(payload).await?;
+
+    let json_files = match serde_json::from_slice::<Vec<JsonFile>>(&body) {
+        Ok(json_files) if !json_files.is_empty() && !query.encrypted => json_files,
+        _ => {
+            let data = stream::once(ready(Ok::<_, UploadError>(Bytes::from(body))));
+            let mut limit = FILE_LIMIT;
+            let name = create_file(
+                data,
+                &database,
+                &owner,
+                password.clone(),
+                &config,
+                &mut limit,
+            )
+            .await?;
+            if query.encrypted {
+                mark_encrypted(&name, &database).await?;
+            }
+            if let Some(hash) = &password {
+                remember_password(&name, hash, &mut cookies, &keys);
+            }
+            // The extension of encrypted pastes is not known.
+            let extension = (!query.encrypted).then(|| "json".to_owned());
+            if let Some(extension) = &extension {
+                database
+                    .set_extension(&name, extension.clone())
+                    .await
+                    .map_err(ErrorInternalServerError)?;
+            }
+            return Ok(response(name, cookies, extension, &config));
+        }
+    };
+
+    let name = database
+        .new_file(owner, Some(config.max_age), password.clone())
+        .await
+        .map_err(ErrorInternalServerError)?
+        .name()
+        .to_owned();
+    let result = async {
+        let mut files = Vec::new();
+        let mut limit = FILE_LIMIT;
+        for JsonFile {
+            name: file_name,
+            content,
+        } in json_files
+        {
+            let file_name = group_file_name(Some(&file_name), &files);
+            let data = stream::once(ready(Ok::<_, UploadError>(Bytes::from(content))));
+            create_group_file(data, &database, &name, &file_name, &mut limit).await?;
+            files.push(file_name);
+        }
+        database
+            .set_group_files(&name, files)
+            .await
+            .map_err(ErrorInternalServerError)?;
+        Ok(())
+    }
+    .await;
+    discard_failed(result, Some(&name), &database).await?;
+    if let Some(hash) = &password {
+        remember_password(&name, hash, &mut cookies, &keys);
+    }
+    Ok(response(name, cookies, None, &config))
+}
+
+/// Fails if the hash of `data` is on the blocklist, for uploads that do not go
+/// through [`write_file`]. Images are also checked without their metadata, as
+/// [`strip_metadata`] would store them.
+async fn check_blocklist(data: &[u8], database: &DB) -> Result<()> {
+    let stripped = images::strip_metadata(data);
+    for data in [Some(data), stripped.as_deref()].into_iter().flatten() {
+        if database
+            .is_blocked(&db::content_hash(data))
+            .await
+            .map_err(ErrorInternalServerError)?
+        {
+            return Err(UploadError::Blocked.into());
+        }
+    }
+    Ok(())
+}
+
+/// Reads `data` into memory, failing if it is larger than [`FILE_LIMIT`].
+async fn read_limited<E: ResponseError + 'static>(
+    mut data: impl Stream<Item = Result<Bytes, E>> + Unpin,
+) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    while let Some(data) = data.try_next().await? {
+        if buf.len() + data.len() > FILE_LIMIT {
+            return Err(UploadError::FieldTooBig("file", FILE_LIMIT).into());
+        }
+        buf.extend_from_slice(&data);
+    }
+    Ok(buf)
+}
+
+/// Reads a short text field, returning it trimmed.
+async fn text_field(
+    mut field: actix_multipart::Field,
+    name: &'static str,
+    limit: usize,
+) -> Result<String> {
+    let mut buf = String::new();
+    while let Some(data) = field.try_next().await? {
+        buf += str::from_utf8(&data)?;
+        if buf.len() > limit {
+            field.for_each(|_| ready(())).await;
+            return Err(UploadError::FieldTooBig(name, limit).into());
+        }
+    }
+    Ok(buf.trim().to_owned())
 }
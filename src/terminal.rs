@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, Theme},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Colors {
+    TrueColor,
+    Ansi256,
+}
+
+impl Colors {
+    /// Parses the value of the `color` query parameter, an empty value selects
+    /// true color.
+    pub fn from_query(value: &str) -> Option<Self> {
+        match value {
+            "" | "24bit" | "truecolor" => Some(Self::TrueColor),
+            "256" => Some(Self::Ansi256),
+            _ => None,
+        }
+    }
+
+    /// Parses an `Accept` header, matching `text/x-ansi` with an optional
+    /// `colors=256` parameter.
+    pub fn from_accept(value: &str) -> Option<Self> {
+        value.split(',').find_map(|media| {
+            let mut params = media.split(';').map(str::trim);
+            if !params
+                .next()
+                .is_some_and(|it| it.eq_ignore_ascii_case("text/x-ansi"))
+            {
+                return None;
+            }
+            Some(
+                if params.any(|it| it.replace(' ', "").eq_ignore_ascii_case("colors=256")) {
+                    Self::Ansi256
+                } else {
+                    Self::TrueColor
+                },
+            )
+        })
+    }
+
+    fn foreground(self, out: &mut String, Color { r, g, b, .. }: Color) {
+        match self {
+            Self::TrueColor => write!(out, "\x1b[38;2;{r};{g};{b}m"),
+            Self::Ansi256 => write!(out, "\x1b[38;5;{}m", ansi256(r, g, b)),
+        }
+        .expect("writing to string does not fail");
+    }
+}
+
+/// Maps a color to the closest entry of the 6x6x6 cube or the grayscale ramp of
+/// the 256 color palette.
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    fn cube(c: u8) -> u8 {
+        match c {
+            0..=47 => 0,
+            48..=114 => 1,
+            _ => (c - 35) / 40,
+        }
+    }
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)]
+            .into_iter()
+            .map(|(a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+
+    let (cr, cg, cb) = (cube(r), cube(g), cube(b));
+    let cube_color = (
+        LEVELS[cr as usize],
+        LEVELS[cg as usize],
+        LEVELS[cb as usize],
+    );
+    let average = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let gray_index = if average > 238 {
+        23
+    } else {
+        average.saturating_sub(3) / 10
+    };
+    let gray = 8 + gray_index * 10;
+
+    if distance((gray, gray, gray)) < distance(cube_color) {
+        232 + gray_index
+    } else {
+        16 + 36 * cr + 6 * cg + cb
+    }
+}
+
+/// Highlights `code` using ANSI escape sequences, every line is terminated by a
+/// reset so pagers like `less -R` don't bleed colors across lines.
+pub fn highlight(
+    code: &str,
+    syntax: &SyntaxReference,
+    syntaxes: &SyntaxSet,
+    theme: &Theme,
+    colors: Colors,
+) -> Result<String, syntect::Error> {
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut out = String::with_capacity(code.len() * 2);
+    for line in LinesWithEndings::from(code) {
+        // Necessary for the used toml syntax
+        let line = &line.replace('\r', "");
+        let mut current = None;
+        for (style, text) in highlighter.highlight_line(line, syntaxes)? {
+            let text = text.trim_end_matches('\n');
+            if text.is_empty() {
+                continue;
+            }
+            if current != Some(style.foreground) {
+                colors.foreground(&mut out, style.foreground);
+                current = Some(style.foreground);
+            }
+            out.push_str(text);
+        }
+        out.push_str(RESET);
+        if line.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,34 @@
+//! Host for raw files separate from the main site, configured as `raw_url`.
+//! Requests for files on the main host are redirected there, see
+//! [`crate::simple`], and the raw host serves nothing but files.
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    web::Data,
+    Error, HttpResponse,
+};
+use actix_web_lab::middleware::Next;
+
+use crate::config::Config;
+
+/// Responds with `404` to requests on the raw host for anything but files.
+pub async fn restrict(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let on_raw_host = request
+        .app_data::<Data<Config>>()
+        .is_some_and(|config| config.is_raw_host(request.connection_info().host()));
+    if on_raw_host && !is_file_path(request.path()) {
+        return Ok(request
+            .into_response(HttpResponse::NotFound().finish())
+            .map_into_right_body());
+    }
+    Ok(next.call(request).await?.map_into_left_body())
+}
+
+fn is_file_path(path: &str) -> bool {
+    let mut segments = path.trim_start_matches('/').split('/');
+    matches!(segments.next(), Some("raw" | "download" | "thumb")) || segments.next() == Some("rev")
+}
@@ -0,0 +1,98 @@
+//! Storage of the database's vault key, sealed with the configured secret.
+//!
+//! BonsaiDB encrypts documents with keys from its vault, the vault itself is
+//! unlocked by a key pair generated when the database is created. By default
+//! that key pair is stored as plain file next to the data, here it is stored
+//! encrypted with XChaCha20-Poly1305 under a key derived from the secret.
+
+use std::{fs, io, path::PathBuf};
+
+use argon2::Argon2;
+use bonsaidb::local::{
+    vault::{self, KeyPair, VaultKeyStorage},
+    StorageId,
+};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use thiserror::Error;
+
+/// Directory inside the database the sealed key pairs are stored in.
+pub const DIRECTORY: &str = "sealed-vault-keys";
+
+/// Fixed salt, the secret is expected to be random and specific to the
+/// instance.
+const SALT: &[u8] = b"pastemp-vault-key";
+const NONCE_LENGTH: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("accessing sealed vault key: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid vault key: {0}")]
+    Key(#[from] vault::Error),
+    #[error("vault key can not be unsealed, the encryption key is probably wrong")]
+    Unseal,
+}
+
+pub struct SealedKeyStorage {
+    directory: PathBuf,
+    cipher: XChaCha20Poly1305,
+}
+
+impl std::fmt::Debug for SealedKeyStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SealedKeyStorage")
+            .field("directory", &self.directory)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SealedKeyStorage {
+    /// Stores keys in [`DIRECTORY`] below `database`.
+    pub fn new(database: impl Into<PathBuf>, secret: &[u8]) -> argon2::Result<Self> {
+        let mut key = [0; 32];
+        Argon2::default().hash_password_into(secret, SALT, &mut key)?;
+        Ok(Self {
+            directory: database.into().join(DIRECTORY),
+            cipher: XChaCha20Poly1305::new(&key.into()),
+        })
+    }
+
+    fn path(&self, storage_id: StorageId) -> PathBuf {
+        self.directory.join(storage_id.to_string())
+    }
+}
+
+impl VaultKeyStorage for SealedKeyStorage {
+    type Error = Error;
+
+    fn set_vault_key_for(&self, storage_id: StorageId, key: KeyPair) -> Result<(), Error> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, key.to_bytes()?.as_slice())
+            .expect("encrypting into a Vec does not fail");
+        fs::create_dir_all(&self.directory)?;
+        fs::write(self.path(storage_id), [nonce.as_slice(), &sealed].concat())?;
+        Ok(())
+    }
+
+    fn vault_key_for(&self, storage_id: StorageId) -> Result<Option<KeyPair>, Error> {
+        let sealed = match fs::read(self.path(storage_id)) {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if sealed.len() < NONCE_LENGTH {
+            return Err(Error::Unseal);
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LENGTH);
+        let key = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| Error::Unseal)?;
+        Ok(Some(KeyPair::from_bytes(&key)?))
+    }
+}